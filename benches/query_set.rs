@@ -0,0 +1,101 @@
+//! Compare the extraction of a dozen paths from many records, done
+//! with a precompiled `QuerySet`, with `extract_many`, and with repeated
+//! single extractions.
+//!
+//! Run with `cargo bench`.
+
+use {
+    criterion::{
+        Criterion,
+        black_box,
+        criterion_group,
+        criterion_main,
+    },
+    iq::*,
+    serde::Serialize,
+    std::collections::HashMap,
+};
+
+#[derive(Serialize)]
+struct Address {
+    street: String,
+    city: String,
+    zip: String,
+}
+
+#[derive(Serialize)]
+struct Record {
+    id: u64,
+    name: String,
+    active: bool,
+    score: f64,
+    address: Address,
+    tags: Vec<String>,
+    metrics: HashMap<String, u32>,
+}
+
+fn records() -> Vec<Record> {
+    (0..1000)
+        .map(|i| Record {
+            id: i,
+            name: format!("record {i}"),
+            active: i % 2 == 0,
+            score: i as f64 / 7.0,
+            address: Address {
+                street: format!("{i} main street"),
+                city: "Paris".to_string(),
+                zip: "75001".to_string(),
+            },
+            tags: (0..10).map(|t| format!("tag-{t}")).collect(),
+            metrics: (0..20).map(|m| (format!("m{m}"), m * i as u32)).collect(),
+        })
+        .collect()
+}
+
+const PATHS: &[&str] = &[
+    "id",
+    "name",
+    "active",
+    "score",
+    "address.street",
+    "address.city",
+    "address.zip",
+    "tags.0",
+    "tags.5",
+    "tags.9",
+    "metrics.m3",
+    "metrics.m17",
+];
+
+fn bench_extractions(c: &mut Criterion) {
+    let records = records();
+    let mut group = c.benchmark_group("12 paths from 1000 records");
+    group.bench_function("extract_primitive", |b| {
+        b.iter(|| {
+            for record in &records {
+                for path in PATHS {
+                    black_box(extract_primitive(record, *path));
+                }
+            }
+        })
+    });
+    group.bench_function("extract_many", |b| {
+        b.iter(|| {
+            for record in &records {
+                black_box(extract_many(record, PATHS));
+            }
+        })
+    });
+    let queries = QuerySet::new(PATHS);
+    group.bench_function("QuerySet", |b| {
+        b.iter(|| {
+            for record in &records {
+                black_box(queries.extract(record));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_extractions);
+criterion_main!(benches);
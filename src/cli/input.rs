@@ -0,0 +1,185 @@
+use {
+    clap::ValueEnum,
+    serde::{
+        Serialize,
+        Serializer,
+    },
+    std::{
+        fmt,
+        path::Path,
+    },
+};
+
+/// The format of a document given to the CLI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InputFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl InputFormat {
+    /// Guess the format from the extension of the file
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "toml" => Some(Self::Toml),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for InputFormat {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        f.write_str(match self {
+            Self::Json => "JSON",
+            Self::Yaml => "YAML",
+            Self::Toml => "TOML",
+        })
+    }
+}
+
+/// A parsed document, kept in the value type of its format so that
+/// nothing is lost (eg YAML non string keys)
+#[derive(Debug)]
+pub enum Document {
+    Json(serde_json::Value),
+    Yaml(serde_norway::Value),
+    Toml(toml::Value),
+}
+
+impl Serialize for Document {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Json(value) => value.serialize(serializer),
+            Self::Yaml(value) => value.serialize(serializer),
+            Self::Toml(value) => value.serialize(serializer),
+        }
+    }
+}
+
+/// The reason why a document can't be parsed
+#[derive(Debug)]
+pub struct ParseError {
+    format: InputFormat,
+    message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        write!(f, "invalid {} document: {}", self.format, self.message)
+    }
+}
+
+impl Document {
+    /// Parse a document in the given format or, when it's not known,
+    /// in the first format accepting it among JSON, TOML and YAML.
+    pub fn parse(
+        text: &str,
+        format: Option<InputFormat>,
+    ) -> Result<Self, ParseError> {
+        if let Some(format) = format {
+            return Self::parse_as(text, format);
+        }
+        // a document starting with a brace is JSON (or a TOML table), it's
+        // not parsed as YAML which would accept a broken JSON
+        let text_start = text.trim_start();
+        let candidates: &[InputFormat] = if text_start.starts_with('{') {
+            &[InputFormat::Json]
+        } else if text_start.starts_with('[') {
+            &[InputFormat::Json, InputFormat::Toml]
+        } else {
+            &[InputFormat::Json, InputFormat::Toml, InputFormat::Yaml]
+        };
+        for &format in candidates {
+            if let Ok(document) = Self::parse_as(text, format) {
+                return Ok(document);
+            }
+        }
+        // reporting the error of the most likely format
+        let likely = if candidates.len() < 3 {
+            InputFormat::Json
+        } else {
+            InputFormat::Yaml
+        };
+        Self::parse_as(text, likely)
+    }
+    fn parse_as(
+        text: &str,
+        format: InputFormat,
+    ) -> Result<Self, ParseError> {
+        let error = |message: String| ParseError { format, message };
+        match format {
+            InputFormat::Json => serde_json::from_str(text)
+                .map(Self::Json)
+                .map_err(|e| error(e.to_string())),
+            InputFormat::Yaml => serde_norway::from_str(text)
+                .map(Self::Yaml)
+                .map_err(|e| error(e.to_string())),
+            InputFormat::Toml => toml::from_str(text)
+                .map(|mut value| {
+                    stringify_datetimes(&mut value);
+                    Self::Toml(value)
+                })
+                .map_err(|e| error(e.to_string())),
+        }
+    }
+}
+
+/// Replace the TOML datetimes with their text, as they would otherwise
+/// be serialized as a private `{"$__toml_private_datetime": ...}` struct
+fn stringify_datetimes(value: &mut toml::Value) {
+    match value {
+        toml::Value::Datetime(datetime) => {
+            *value = toml::Value::String(datetime.to_string());
+        }
+        toml::Value::Array(items) => items.iter_mut().for_each(stringify_datetimes),
+        toml::Value::Table(table) => table.iter_mut().for_each(|(_, v)| stringify_datetimes(v)),
+        _ => {}
+    }
+}
+
+#[test]
+fn test_parse_document() {
+    use iq::IQ;
+    let parse = |text| Document::parse(text, None).unwrap();
+    assert!(matches!(parse(r#"{"a": [1, 2]}"#), Document::Json(_)));
+    assert!(matches!(parse("[a]\nb = 2"), Document::Toml(_)));
+    assert!(matches!(parse("a:\n  - 1\n  - 2"), Document::Yaml(_)));
+    assert_eq!(parse("[a]\nb = 2").extract_primitive("a.b").unwrap(), "2");
+    assert_eq!(
+        parse("a:\n  - 1\n  - 2").extract_json("a").unwrap(),
+        "[1,2]"
+    );
+    let err = Document::parse(r#"{"a": }"#, None).unwrap_err();
+    assert_eq!(err.format, InputFormat::Json);
+    let err = Document::parse("[1, 2", None).unwrap_err();
+    assert_eq!(err.format, InputFormat::Json);
+    assert!(Document::parse("a: 1", Some(InputFormat::Json)).is_err());
+    let toml = "[release]\ndate = 2024-05-27\nat = [1979-05-27T07:32:00Z]";
+    let release = parse(toml);
+    assert_eq!(
+        release.extract_primitive("release.date").unwrap(),
+        "2024-05-27"
+    );
+    assert_eq!(
+        release.extract_json("release.at").unwrap(),
+        r#"["1979-05-27T07:32:00Z"]"#
+    );
+    assert_eq!(
+        InputFormat::from_path(Path::new("conf/app.YML")),
+        Some(InputFormat::Yaml)
+    );
+    assert_eq!(InputFormat::from_path(Path::new("dump")), None);
+}
@@ -0,0 +1,184 @@
+//! The `iq` command: query a JSON, YAML or TOML document with an iq path.
+//!
+//! Exit codes:
+//! - 0: the value was found and printed
+//! - 1: there's no value at the path (or it can't be rendered in the
+//!   requested mode, eg a map in primitive mode)
+//! - 2: invalid arguments
+//! - 3: the document can't be parsed
+//! - 4: the document can't be read
+//!
+//! With `--explore`, the document is explored interactively instead.
+mod input;
+mod repl;
+
+use {
+    clap::{
+        Parser,
+        ValueEnum,
+    },
+    input::*,
+    iq::*,
+    std::{
+        fmt,
+        fs,
+        io::{
+            self,
+            Read,
+        },
+        path::{
+            Path,
+            PathBuf,
+        },
+        process,
+    },
+};
+
+const EXIT_NOT_FOUND: i32 = 1;
+const EXIT_INVALID_DOCUMENT: i32 = 3;
+const EXIT_IO: i32 = 4;
+
+/// Query a JSON, YAML or TOML document with an iq path
+#[derive(Debug, Parser)]
+#[command(name = "iq", version, about)]
+struct Args {
+    /// Path of the value, eg `passengers.1.name` (`.` for the whole document)
+    #[arg(required_unless_present = "explore")]
+    path: Option<String>,
+
+    /// File to read, stdin if absent or `-`
+    file: Option<PathBuf>,
+
+    /// Format of the document, guessed from the file extension or
+    /// the content if absent
+    #[arg(short, long, value_enum)]
+    input: Option<InputFormat>,
+
+    /// How to print the value
+    #[arg(short, long, value_enum, default_value = "json")]
+    output: OutputMode,
+
+    /// Explore the document interactively (`help` for the commands)
+    #[arg(short, long, value_name = "FILE", conflicts_with_all = ["path", "file"])]
+    explore: Option<PathBuf>,
+}
+
+/// How the found value is printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputMode {
+    /// The value as a raw string, only if it's a "primitive"
+    Primitive,
+    /// Compact JSON
+    Json,
+    /// Pretty JSON
+    Pretty,
+    /// The number of items of the array/map/string
+    Size,
+}
+
+impl From<OutputMode> for IqFormat {
+    fn from(mode: OutputMode) -> Self {
+        match mode {
+            OutputMode::Primitive => Self::Primitive,
+            OutputMode::Json => Self::Json,
+            OutputMode::Pretty => Self::JsonPretty,
+            OutputMode::Size => Self::Size,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum CliError {
+    Io(io::Error),
+    Parse(ParseError),
+    Query(IqError),
+    Readline(rustyline::error::ReadlineError),
+}
+
+impl CliError {
+    fn exit_code(&self) -> i32 {
+        match self {
+            Self::Io(_) | Self::Readline(_) => EXIT_IO,
+            Self::Parse(_) | Self::Query(_) => EXIT_INVALID_DOCUMENT,
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "can't read the document: {}", e),
+            Self::Parse(e) => e.fmt(f),
+            Self::Query(e) => e.fmt(f),
+            Self::Readline(e) => write!(f, "can't read the command: {}", e),
+        }
+    }
+}
+
+/// Read the document, from the file or from stdin
+fn load(
+    file: Option<&Path>,
+    format: Option<InputFormat>,
+) -> Result<Document, CliError> {
+    let (text, format) = match file {
+        Some(file) if file.as_os_str() != "-" => {
+            let text = fs::read_to_string(file).map_err(CliError::Io)?;
+            (text, format.or_else(|| InputFormat::from_path(file)))
+        }
+        _ => {
+            let mut text = String::new();
+            io::stdin()
+                .read_to_string(&mut text)
+                .map_err(CliError::Io)?;
+            (text, format)
+        }
+    };
+    Document::parse(&text, format).map_err(CliError::Parse)
+}
+
+/// Extract the value at a path given by the user, `.` or an empty
+/// path designating the whole document
+fn extract(
+    document: &Document,
+    path: &str,
+    format: IqFormat,
+) -> Result<Option<String>, IqError> {
+    if path.is_empty() || path == "." {
+        extract_string_checked(document, Vec::<&str>::new(), format)
+    } else {
+        extract_string_checked(document, path, format)
+    }
+}
+
+/// Print the value at the path, return whether it was found
+fn run(args: &Args) -> Result<bool, CliError> {
+    if let Some(file) = &args.explore {
+        let document = load(Some(file), args.input)?;
+        repl::run(document).map_err(CliError::Readline)?;
+        return Ok(true);
+    }
+    let document = load(args.file.as_deref(), args.input)?;
+    let path = args.path.as_deref().unwrap_or(".");
+    match extract(&document, path, args.output.into()).map_err(CliError::Query)? {
+        Some(value) => {
+            println!("{}", value);
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+    match run(&args) {
+        Ok(true) => {}
+        Ok(false) => process::exit(EXIT_NOT_FOUND),
+        Err(e) => {
+            eprintln!("iq: {}", e);
+            process::exit(e.exit_code());
+        }
+    }
+}
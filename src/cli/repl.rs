@@ -0,0 +1,370 @@
+use {
+    crate::input::Document,
+    iq::*,
+    rustyline::{
+        Context,
+        Editor,
+        Helper,
+        completion::Completer,
+        error::ReadlineError,
+        highlight::Highlighter,
+        hint::Hinter,
+        history::DefaultHistory,
+        validate::Validator,
+    },
+    std::{
+        borrow::Cow,
+        ops::ControlFlow,
+    },
+};
+
+const HELP: &str = "\
+Commands:
+  ls [path]    list the keys of the current value, or of the value at path
+  cd [path]    go to the value at path (`..` for the parent, `/` for the root)
+  cat [path]   print the current value, or the value at path
+  pwd          print the current location
+  help         print this help
+  exit         quit
+Paths are relative to the current location, their parts separated by `/`.
+A key containing a `/` or spaces is written as a JSON string (eg \"a/b\").";
+
+/// What the explorer answers to a command
+#[derive(Debug, PartialEq)]
+pub enum Reply {
+    Text(String),
+    Quit,
+}
+
+/// A short description of a value, as listed by `ls`
+enum Summary {
+    Primitive(String),
+    Compound(Option<usize>),
+}
+
+impl Summary {
+    fn of(node: IqNode<'_>) -> Self {
+        match node.primitive() {
+            Some(primitive) => Self::Primitive(primitive),
+            None => Self::Compound(node.size()),
+        }
+    }
+}
+
+/// Split a path of the explorer in its parts, separated by the `/`
+/// which aren't in a quoted part
+fn split_parts(path: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (i, c) in path.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            '/' if !in_quotes => {
+                parts.push(&path[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&path[start..]);
+    parts
+}
+
+/// The key written in a part of a path, which is literal unless it's
+/// a JSON string
+fn unquote(part: &str) -> Cow<'_, str> {
+    if part.starts_with('"') {
+        if let Ok(key) = serde_json::from_str::<String>(part) {
+            return Cow::Owned(key);
+        }
+    }
+    Cow::Borrowed(part)
+}
+
+/// Write a key as a part of a path, quoted when it couldn't be read back
+fn quote(key: &str) -> Cow<'_, str> {
+    let needs_quotes = matches!(key, "" | "." | "..")
+        || key.starts_with('"')
+        || key.contains(|c: char| c == '/' || c.is_whitespace());
+    if needs_quotes {
+        Cow::Owned(serde_json::Value::from(key).to_string())
+    } else {
+        Cow::Borrowed(key)
+    }
+}
+
+/// The state of an exploration: the document and the current location
+pub struct Explorer {
+    document: Document,
+    /// the literal keys leading to the current value
+    location: Vec<String>,
+}
+
+impl Explorer {
+    pub fn new(document: Document) -> Self {
+        Self {
+            document,
+            location: Vec::new(),
+        }
+    }
+    /// The location designated by a path relative to the current one
+    fn resolve(
+        &self,
+        path: &str,
+    ) -> Vec<String> {
+        let mut location = if path.starts_with('/') {
+            Vec::new()
+        } else {
+            self.location.clone()
+        };
+        for part in split_parts(path) {
+            match part {
+                "" | "." => {}
+                ".." => {
+                    location.pop();
+                }
+                _ => location.push(unquote(part).into_owned()),
+            }
+        }
+        location
+    }
+    /// The keys of the value at the location, with a summary of
+    /// their values
+    fn children(
+        &self,
+        location: &[String],
+    ) -> Vec<(String, Summary)> {
+        let mut children = Vec::new();
+        let _ = for_each_child(&self.document, location, |path, node| {
+            if let Some(key) = path.last() {
+                children.push((key.clone(), Summary::of(node)));
+            }
+            ControlFlow::Continue(())
+        });
+        children
+    }
+    /// Check there's a map or an array at the location
+    fn check_compound(
+        &self,
+        location: &[String],
+    ) -> Result<(), String> {
+        if extract_primitive(&self.document, location).is_some() {
+            Err(format!(
+                "{} isn't a map or an array",
+                Self::display_location(location)
+            ))
+        } else if extract_size(&self.document, location).is_none() {
+            Err(format!("nothing at {}", Self::display_location(location)))
+        } else {
+            Ok(())
+        }
+    }
+    fn display_location(location: &[String]) -> String {
+        let parts: Vec<Cow<'_, str>> = location.iter().map(|key| quote(key)).collect();
+        format!("/{}", parts.join("/"))
+    }
+    /// Execute a command line
+    pub fn execute(
+        &mut self,
+        line: &str,
+    ) -> Result<Reply, String> {
+        let line = line.trim();
+        let (command, arg) = line.split_once(' ').unwrap_or((line, ""));
+        let location = self.resolve(arg.trim());
+        match command {
+            "" => Ok(Reply::Text(String::new())),
+            "ls" => {
+                self.check_compound(&location)?;
+                let children = self.children(&location);
+                let width = children.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+                let lines: Vec<String> = children
+                    .into_iter()
+                    .map(|(key, summary)| match summary {
+                        Summary::Primitive(value) => format!("{key:width$}  = {value}"),
+                        Summary::Compound(Some(size)) => format!("{key:width$}  ({size})"),
+                        Summary::Compound(None) => key,
+                    })
+                    .collect();
+                Ok(Reply::Text(lines.join("\n")))
+            }
+            "cd" => {
+                self.check_compound(&location)?;
+                self.location = location;
+                Ok(Reply::Text(String::new()))
+            }
+            "cat" => {
+                if let Some(primitive) = extract_primitive(&self.document, &location) {
+                    return Ok(Reply::Text(primitive));
+                }
+                extract_json_pretty(&self.document, &location)
+                    .map(Reply::Text)
+                    .ok_or_else(|| format!("nothing at {}", Self::display_location(&location)))
+            }
+            "pwd" => Ok(Reply::Text(Self::display_location(&self.location))),
+            "help" => Ok(Reply::Text(HELP.to_string())),
+            "exit" | "quit" => Ok(Reply::Quit),
+            _ => Err(format!("unknown command: {:?} (try `help`)", command)),
+        }
+    }
+    /// Complete the path being typed with the keys of the value it
+    /// designates (quoted when needed), compound values being suffixed
+    /// with a `/`
+    fn complete_path(
+        &self,
+        partial: &str,
+    ) -> Vec<String> {
+        let start = split_parts(partial).pop().unwrap_or_default();
+        let dir = &partial[..partial.len() - start.len()];
+        let location = self.resolve(dir);
+        self.children(&location)
+            .into_iter()
+            .filter_map(|(key, summary)| {
+                let part = quote(&key);
+                if !part.starts_with(start) && !key.starts_with(start) {
+                    return None;
+                }
+                Some(match summary {
+                    Summary::Compound(_) => format!("{}{}/", dir, part),
+                    Summary::Primitive(_) => format!("{}{}", dir, part),
+                })
+            })
+            .collect()
+    }
+}
+
+impl Completer for Explorer {
+    type Candidate = String;
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        // the path is all that follows the command (it may contain
+        // spaces when quoted)
+        let line = &line[..pos];
+        let Some(idx) = line.find(' ') else {
+            return Ok((0, Vec::new())); // no completion of commands
+        };
+        let arg = line[idx..].trim_start();
+        Ok((pos - arg.len(), self.complete_path(arg)))
+    }
+}
+impl Hinter for Explorer {
+    type Hint = String;
+}
+impl Highlighter for Explorer {}
+impl Validator for Explorer {}
+impl Helper for Explorer {}
+
+/// Run the interactive exploration of the document, until the user quits
+pub fn run(document: Document) -> rustyline::Result<()> {
+    let mut editor: Editor<Explorer, DefaultHistory> = Editor::new()?;
+    editor.set_helper(Some(Explorer::new(document)));
+    loop {
+        let Some(explorer) = editor.helper() else {
+            return Ok(());
+        };
+        let prompt = format!("{}> ", Explorer::display_location(&explorer.location));
+        let line = match editor.readline(&prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        editor.add_history_entry(line.as_str())?;
+        let Some(explorer) = editor.helper_mut() else {
+            return Ok(());
+        };
+        match explorer.execute(&line) {
+            Ok(Reply::Text(text)) if text.is_empty() => {}
+            Ok(Reply::Text(text)) => println!("{}", text),
+            Ok(Reply::Quit) => return Ok(()),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+}
+
+#[test]
+fn test_explorer() {
+    let document = Document::parse(
+        r#"{
+            "ship": "Nostromo",
+            "crew": [
+                {"name": "Ripley", "role": "warrant officer"},
+                {"name": "Ash", "role": "science officer", "android": true}
+            ]
+        }"#,
+        None,
+    )
+    .unwrap();
+    let mut explorer = Explorer::new(document);
+    let mut exec = |line| match explorer.execute(line) {
+        Ok(Reply::Text(text)) => text,
+        Ok(Reply::Quit) => "quit".to_string(),
+        Err(e) => format!("error: {}", e),
+    };
+    assert_eq!(exec("ls"), "crew  (2)\nship  = Nostromo");
+    assert_eq!(exec("cd crew/1"), "");
+    assert_eq!(exec("pwd"), "/crew/1");
+    assert_eq!(
+        exec("ls"),
+        "android  = true\nname     = Ash\nrole     = science officer"
+    );
+    assert_eq!(exec("cat name"), "Ash");
+    assert_eq!(
+        exec("cd name"),
+        "error: /crew/1/name isn't a map or an array"
+    );
+    assert_eq!(exec("cd ../0"), "");
+    assert_eq!(exec("pwd"), "/crew/0");
+    assert_eq!(exec("cat /crew/1/android"), "true");
+    assert_eq!(exec("cat ../../ship"), "Nostromo");
+    assert_eq!(exec("cat role.rank"), "error: nothing at /crew/0/role.rank");
+    assert_eq!(exec("cd /"), "");
+    assert_eq!(
+        exec("cat crew/0"),
+        "{\n  \"name\": \"Ripley\",\n  \"role\": \"warrant officer\"\n}"
+    );
+    assert_eq!(exec("cd captain"), "error: nothing at /captain");
+    assert!(exec("jump").starts_with("error: unknown command"));
+    assert_eq!(exec("exit"), "quit");
+    assert_eq!(explorer.complete_path("cr"), vec!["crew/"]);
+    assert_eq!(
+        explorer.complete_path("crew/1/"),
+        vec!["crew/1/android", "crew/1/name", "crew/1/role"]
+    );
+    assert_eq!(explorer.complete_path("crew/1/r"), vec!["crew/1/role"]);
+    assert!(explorer.complete_path("ship/").is_empty());
+
+    // keys are literal, and quoted when needed
+    let document = Document::parse(
+        r#"{"a.b": {"*": 1, "c/d": {"e f": 2}}, "a": {"b": 3}}"#,
+        None,
+    )
+    .unwrap();
+    let mut explorer = Explorer::new(document);
+    let mut exec = |line| match explorer.execute(line) {
+        Ok(Reply::Text(text)) => text,
+        Ok(Reply::Quit) => "quit".to_string(),
+        Err(e) => format!("error: {}", e),
+    };
+    assert_eq!(exec("ls a.b"), "*    = 1\nc/d  (1)");
+    assert_eq!(exec("cd a.b"), "");
+    assert_eq!(exec("cat *"), "1");
+    assert_eq!(exec(r#"cat "c/d"/"e f""#), "2");
+    assert_eq!(exec(r#"cd "c/d""#), "");
+    assert_eq!(exec("pwd"), r#"/a.b/"c/d""#);
+    assert_eq!(exec("cd /a"), "");
+    assert_eq!(exec("ls"), "b  = 3");
+    assert_eq!(explorer.complete_path("/a.b/c"), vec![r#"/a.b/"c/d"/"#]);
+    assert_eq!(explorer.complete_path(r#"/a.b/"c"#), vec![r#"/a.b/"c/d"/"#]);
+    assert_eq!(
+        explorer.complete_path(r#"/a.b/"c/d"/e"#),
+        vec![r#"/a.b/"c/d"/"e f""#]
+    );
+}
@@ -0,0 +1,891 @@
+use {
+    crate::{
+        errors::IqInternalError,
+        map_key::MapKey,
+        pattern::PathPattern,
+        *,
+    },
+    serde::{
+        Serialize,
+        ser,
+    },
+    serde_json::Value,
+    std::collections::HashMap,
+};
+
+/// The kind of a change found by [diff]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    /// The path exists only in the new value
+    Added,
+    /// The path exists only in the old value
+    Removed,
+    /// The path exists in both values, with different values
+    Modified,
+}
+
+/// A difference between two values, at a given path
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub path: Vec<String>,
+    pub kind: ChangeKind,
+    /// The old value (None when the value was added)
+    pub old: Option<Value>,
+    /// The new value (None when the value was removed)
+    pub new: Option<Value>,
+}
+
+/// Options for [diff_with_options], built from the default ones
+/// with the builder methods
+#[derive(Debug, Clone)]
+pub struct DiffOptions {
+    /// Whether two maps with the same entries in different orders
+    /// are considered equal (default: true).
+    ///
+    /// When false, a map whose entries were reordered is reported as
+    /// modified as a whole.
+    pub unordered_maps: bool,
+    ignored: Vec<PathPattern>,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            unordered_maps: true,
+            ignored: Vec::new(),
+        }
+    }
+}
+
+impl DiffOptions {
+    /// Set whether two maps with the same entries in different orders
+    /// are considered equal
+    pub fn unordered_maps(
+        mut self,
+        unordered_maps: bool,
+    ) -> Self {
+        self.unordered_maps = unordered_maps;
+        self
+    }
+    /// Ignore the paths matching the given pattern, and everything below.
+    ///
+    /// A `*` key in the pattern matches any key or index.
+    pub fn ignore<P: IqPath>(
+        mut self,
+        pattern: P,
+    ) -> Self {
+        self.ignored.push(PathPattern::new(pattern));
+        self
+    }
+    fn is_ignored(
+        &self,
+        path: &[String],
+    ) -> bool {
+        self.ignored.iter().any(|p| p.matches_prefix_of(path))
+    }
+}
+
+impl Change {
+    /// Build the JSON Patch ([RFC 6902](https://www.rfc-editor.org/rfc/rfc6902))
+    /// operation applying this change
+    pub fn to_json_patch_operation(&self) -> Value {
+        let mut op = serde_json::Map::new();
+        let name = match self.kind {
+            ChangeKind::Added => "add",
+            ChangeKind::Removed => "remove",
+            ChangeKind::Modified => "replace",
+        };
+        op.insert("op".to_string(), name.into());
+        op.insert("path".to_string(), json_pointer(&self.path).into());
+        if let Some(new) = &self.new {
+            op.insert("value".to_string(), new.clone());
+        }
+        Value::Object(op)
+    }
+}
+
+/// Build a JSON Patch ([RFC 6902](https://www.rfc-editor.org/rfc/rfc6902))
+/// document from changes computed by [diff]
+pub fn to_json_patch(changes: &[Change]) -> Value {
+    Value::Array(
+        changes
+            .iter()
+            .map(Change::to_json_patch_operation)
+            .collect(),
+    )
+}
+
+fn json_pointer(path: &[String]) -> String {
+    let mut pointer = String::new();
+    for key in path {
+        pointer.push('/');
+        pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+    }
+    pointer
+}
+
+/// Compute the changes between two values.
+///
+/// Sequences are compared by index. When the new sequence is shorter,
+/// the removed items are listed from the last one, so that the changes
+/// can be applied in order (eg as a JSON Patch).
+///
+/// Map keys are rendered as in the paths of the other functions (a
+/// composite key like `(3, 4)` is rendered as `[3,4]`), and numbers are
+/// compared without loss of precision (`1` and `1.0` are different).
+/// An error is returned only when a value fails to serialize.
+///
+/// ```
+/// use iq::ChangeKind;
+///
+/// let old = serde_json::json!({ "name": "Rex", "ears": 2, "tags": ["a", "b"] });
+/// let new = serde_json::json!({ "name": "Rex", "ears": 1, "tags": ["a"] });
+/// let changes = iq::diff(&old, &new).unwrap();
+/// assert_eq!(changes.len(), 2);
+/// assert_eq!(changes[0].path, vec!["ears"]);
+/// assert_eq!(changes[0].kind, ChangeKind::Modified);
+/// assert_eq!(changes[1].path, vec!["tags", "1"]);
+/// assert_eq!(changes[1].kind, ChangeKind::Removed);
+/// assert_eq!(
+///     iq::to_json_patch(&changes),
+///     serde_json::json!([
+///         { "op": "replace", "path": "/ears", "value": 1 },
+///         { "op": "remove", "path": "/tags/1" },
+///     ]),
+/// );
+/// ```
+pub fn diff<A: Serialize, B: Serialize>(
+    old: &A,
+    new: &B,
+) -> Result<Vec<Change>, IqError> {
+    diff_with_options(old, new, &DiffOptions::default())
+}
+
+/// Compute the changes between two values, with options
+///
+/// ```
+/// use iq::DiffOptions;
+///
+/// let old = serde_json::json!({ "name": "Rex", "seen": "monday" });
+/// let new = serde_json::json!({ "name": "Max", "seen": "tuesday" });
+/// let options = DiffOptions::default().unordered_maps(false).ignore("seen");
+/// let changes = iq::diff_with_options(&old, &new, &options).unwrap();
+/// assert_eq!(changes.len(), 1);
+/// assert_eq!(changes[0].path, vec!["name"]);
+/// ```
+pub fn diff_with_options<A: Serialize, B: Serialize>(
+    old: &A,
+    new: &B,
+    options: &DiffOptions,
+) -> Result<Vec<Change>, IqError> {
+    let old = Node::from_serialize(old)?;
+    let new = Node::from_serialize(new)?;
+    let mut changes = Vec::new();
+    let mut path = Vec::new();
+    diff_nodes(&old, &new, &mut path, options, &mut changes);
+    Ok(changes)
+}
+
+/// A JSON like value, but with the order of map entries kept and
+/// without the loss of numbers precision
+#[derive(Debug)]
+enum Node {
+    Scalar(Scalar),
+    Seq(Vec<Node>),
+    Map(Vec<(String, Node)>),
+}
+
+/// A primitive value, as serialized
+#[derive(Debug)]
+enum Scalar {
+    Null,
+    Bool(bool),
+    Int(i128),
+    /// An u128 too big for an i128
+    BigUInt(u128),
+    Float(f64),
+    Str(String),
+    Bytes(Vec<u8>),
+}
+
+impl PartialEq for Scalar {
+    fn eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        match (self, other) {
+            (Self::Null, Self::Null) => true,
+            (Self::Bool(a), Self::Bool(b)) => a == b,
+            (Self::Int(a), Self::Int(b)) => a == b,
+            (Self::BigUInt(a), Self::BigUInt(b)) => a == b,
+            (Self::Float(a), Self::Float(b)) => a == b || (a.is_nan() && b.is_nan()),
+            (Self::Str(a), Self::Str(b)) => a == b,
+            (Self::Bytes(a), Self::Bytes(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Scalar {
+    /// Convert to JSON, which can't hold the integers out of the
+    /// i64 and u64 ranges (they're converted to floats) nor the
+    /// non finite floats (they're converted to null)
+    fn to_value(&self) -> Value {
+        match self {
+            Self::Null => Value::Null,
+            Self::Bool(b) => Value::Bool(*b),
+            Self::Int(int) => {
+                if let Ok(int) = i64::try_from(*int) {
+                    int.into()
+                } else if let Ok(int) = u64::try_from(*int) {
+                    int.into()
+                } else {
+                    (*int as f64).into()
+                }
+            }
+            Self::BigUInt(int) => (*int as f64).into(),
+            Self::Float(float) => (*float).into(),
+            Self::Str(s) => s.as_str().into(),
+            Self::Bytes(bytes) => bytes.as_slice().into(),
+        }
+    }
+}
+
+impl Node {
+    fn from_serialize<T: Serialize>(value: &T) -> Result<Self, IqError> {
+        match value.serialize(NodeBuilder) {
+            Ok(node) => Ok(node),
+            Err(IqInternalError::Json(err)) => Err(IqError::Json(err)),
+            Err(err) => Err(IqError::Serde(err.to_string())),
+        }
+    }
+    fn to_value(&self) -> Value {
+        match self {
+            Self::Scalar(scalar) => scalar.to_value(),
+            Self::Seq(items) => Value::Array(items.iter().map(Self::to_value).collect()),
+            Self::Map(entries) => Value::Object(
+                entries
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.to_value()))
+                    .collect(),
+            ),
+        }
+    }
+    fn int<I: Into<i128>>(int: I) -> Self {
+        Self::Scalar(Scalar::Int(int.into()))
+    }
+    /// Wrap the content of an enum variant, like serde_json does
+    fn tagged(
+        variant: Option<&str>,
+        node: Self,
+    ) -> Self {
+        match variant {
+            Some(variant) => Self::Map(vec![(variant.to_string(), node)]),
+            None => node,
+        }
+    }
+}
+
+/// The serializer building a node.
+///
+/// Enum variants are represented as by serde_json (`{"Variant": content}`),
+/// map keys are rendered as in paths (see `MapKey`)
+struct NodeBuilder;
+
+struct SeqBuilder {
+    variant: Option<&'static str>,
+    items: Vec<Node>,
+}
+
+struct MapBuilder {
+    variant: Option<&'static str>,
+    key: MapKey,
+    entries: Vec<(String, Node)>,
+}
+
+impl SeqBuilder {
+    fn new(
+        variant: Option<&'static str>,
+        len: Option<usize>,
+    ) -> Self {
+        Self {
+            variant,
+            items: Vec::with_capacity(len.unwrap_or(0)),
+        }
+    }
+    fn push<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), IqInternalError> {
+        self.items.push(value.serialize(NodeBuilder)?);
+        Ok(())
+    }
+    fn end(self) -> Result<Node, IqInternalError> {
+        Ok(Node::tagged(self.variant, Node::Seq(self.items)))
+    }
+}
+
+impl MapBuilder {
+    fn new(
+        variant: Option<&'static str>,
+        len: Option<usize>,
+    ) -> Self {
+        Self {
+            variant,
+            key: MapKey::default(),
+            entries: Vec::with_capacity(len.unwrap_or(0)),
+        }
+    }
+    fn push<T: ?Sized + Serialize>(
+        &mut self,
+        key: &str,
+        value: &T,
+    ) -> Result<(), IqInternalError> {
+        self.entries
+            .push((key.to_string(), value.serialize(NodeBuilder)?));
+        Ok(())
+    }
+    fn end(self) -> Result<Node, IqInternalError> {
+        Ok(Node::tagged(self.variant, Node::Map(self.entries)))
+    }
+}
+
+impl ser::Serializer for NodeBuilder {
+    type Ok = Node;
+    type Error = IqInternalError;
+    type SerializeSeq = SeqBuilder;
+    type SerializeTuple = SeqBuilder;
+    type SerializeTupleStruct = SeqBuilder;
+    type SerializeTupleVariant = SeqBuilder;
+    type SerializeMap = MapBuilder;
+    type SerializeStruct = MapBuilder;
+    type SerializeStructVariant = MapBuilder;
+    fn serialize_bool(
+        self,
+        v: bool,
+    ) -> Result<Node, IqInternalError> {
+        Ok(Node::Scalar(Scalar::Bool(v)))
+    }
+    fn serialize_i8(
+        self,
+        v: i8,
+    ) -> Result<Node, IqInternalError> {
+        Ok(Node::int(v))
+    }
+    fn serialize_i16(
+        self,
+        v: i16,
+    ) -> Result<Node, IqInternalError> {
+        Ok(Node::int(v))
+    }
+    fn serialize_i32(
+        self,
+        v: i32,
+    ) -> Result<Node, IqInternalError> {
+        Ok(Node::int(v))
+    }
+    fn serialize_i64(
+        self,
+        v: i64,
+    ) -> Result<Node, IqInternalError> {
+        Ok(Node::int(v))
+    }
+    fn serialize_i128(
+        self,
+        v: i128,
+    ) -> Result<Node, IqInternalError> {
+        Ok(Node::int(v))
+    }
+    fn serialize_u8(
+        self,
+        v: u8,
+    ) -> Result<Node, IqInternalError> {
+        Ok(Node::int(v))
+    }
+    fn serialize_u16(
+        self,
+        v: u16,
+    ) -> Result<Node, IqInternalError> {
+        Ok(Node::int(v))
+    }
+    fn serialize_u32(
+        self,
+        v: u32,
+    ) -> Result<Node, IqInternalError> {
+        Ok(Node::int(v))
+    }
+    fn serialize_u64(
+        self,
+        v: u64,
+    ) -> Result<Node, IqInternalError> {
+        Ok(Node::int(v))
+    }
+    fn serialize_u128(
+        self,
+        v: u128,
+    ) -> Result<Node, IqInternalError> {
+        Ok(Node::Scalar(
+            i128::try_from(v).map_or(Scalar::BigUInt(v), Scalar::Int),
+        ))
+    }
+    fn serialize_f32(
+        self,
+        v: f32,
+    ) -> Result<Node, IqInternalError> {
+        Ok(Node::Scalar(Scalar::Float(v.into())))
+    }
+    fn serialize_f64(
+        self,
+        v: f64,
+    ) -> Result<Node, IqInternalError> {
+        Ok(Node::Scalar(Scalar::Float(v)))
+    }
+    fn serialize_char(
+        self,
+        v: char,
+    ) -> Result<Node, IqInternalError> {
+        Ok(Node::Scalar(Scalar::Str(v.to_string())))
+    }
+    fn serialize_str(
+        self,
+        v: &str,
+    ) -> Result<Node, IqInternalError> {
+        Ok(Node::Scalar(Scalar::Str(v.to_string())))
+    }
+    fn serialize_bytes(
+        self,
+        v: &[u8],
+    ) -> Result<Node, IqInternalError> {
+        Ok(Node::Scalar(Scalar::Bytes(v.to_vec())))
+    }
+    fn serialize_none(self) -> Result<Node, IqInternalError> {
+        Ok(Node::Scalar(Scalar::Null))
+    }
+    fn serialize_some<T>(
+        self,
+        value: &T,
+    ) -> Result<Node, IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<Node, IqInternalError> {
+        Ok(Node::Scalar(Scalar::Null))
+    }
+    fn serialize_unit_struct(
+        self,
+        _name: &'static str,
+    ) -> Result<Node, IqInternalError> {
+        Ok(Node::Scalar(Scalar::Null))
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Node, IqInternalError> {
+        self.serialize_str(variant)
+    }
+    fn serialize_newtype_struct<T>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Node, IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Node, IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(Node::tagged(Some(variant), value.serialize(self)?))
+    }
+    fn serialize_seq(
+        self,
+        len: Option<usize>,
+    ) -> Result<SeqBuilder, IqInternalError> {
+        Ok(SeqBuilder::new(None, len))
+    }
+    fn serialize_tuple(
+        self,
+        len: usize,
+    ) -> Result<SeqBuilder, IqInternalError> {
+        Ok(SeqBuilder::new(None, Some(len)))
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqBuilder, IqInternalError> {
+        Ok(SeqBuilder::new(None, Some(len)))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SeqBuilder, IqInternalError> {
+        Ok(SeqBuilder::new(Some(variant), Some(len)))
+    }
+    fn serialize_map(
+        self,
+        len: Option<usize>,
+    ) -> Result<MapBuilder, IqInternalError> {
+        Ok(MapBuilder::new(None, len))
+    }
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<MapBuilder, IqInternalError> {
+        Ok(MapBuilder::new(None, Some(len)))
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<MapBuilder, IqInternalError> {
+        Ok(MapBuilder::new(Some(variant), Some(len)))
+    }
+}
+impl ser::SerializeSeq for SeqBuilder {
+    type Ok = Node;
+    type Error = IqInternalError;
+    fn serialize_element<T>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+    fn end(self) -> Result<Node, IqInternalError> {
+        SeqBuilder::end(self)
+    }
+}
+impl ser::SerializeTuple for SeqBuilder {
+    type Ok = Node;
+    type Error = IqInternalError;
+    fn serialize_element<T>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+    fn end(self) -> Result<Node, IqInternalError> {
+        SeqBuilder::end(self)
+    }
+}
+impl ser::SerializeTupleStruct for SeqBuilder {
+    type Ok = Node;
+    type Error = IqInternalError;
+    fn serialize_field<T>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+    fn end(self) -> Result<Node, IqInternalError> {
+        SeqBuilder::end(self)
+    }
+}
+impl ser::SerializeTupleVariant for SeqBuilder {
+    type Ok = Node;
+    type Error = IqInternalError;
+    fn serialize_field<T>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+    fn end(self) -> Result<Node, IqInternalError> {
+        SeqBuilder::end(self)
+    }
+}
+impl ser::SerializeMap for MapBuilder {
+    type Ok = Node;
+    type Error = IqInternalError;
+    fn serialize_key<T>(
+        &mut self,
+        key: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.key.render(key)?;
+        Ok(())
+    }
+    fn serialize_value<T>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        let node = value.serialize(NodeBuilder)?;
+        self.entries.push((self.key.as_str().to_string(), node));
+        Ok(())
+    }
+    fn end(self) -> Result<Node, IqInternalError> {
+        MapBuilder::end(self)
+    }
+}
+impl ser::SerializeStruct for MapBuilder {
+    type Ok = Node;
+    type Error = IqInternalError;
+    fn serialize_field<T>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(key, value)
+    }
+    fn end(self) -> Result<Node, IqInternalError> {
+        MapBuilder::end(self)
+    }
+}
+impl ser::SerializeStructVariant for MapBuilder {
+    type Ok = Node;
+    type Error = IqInternalError;
+    fn serialize_field<T>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(key, value)
+    }
+    fn end(self) -> Result<Node, IqInternalError> {
+        MapBuilder::end(self)
+    }
+}
+
+fn push_change(
+    path: &[String],
+    kind: ChangeKind,
+    old: Option<&Node>,
+    new: Option<&Node>,
+    changes: &mut Vec<Change>,
+) {
+    changes.push(Change {
+        path: path.to_vec(),
+        kind,
+        old: old.map(Node::to_value),
+        new: new.map(Node::to_value),
+    });
+}
+
+/// Index the entries of a map by key (the first entry wins when
+/// several keys are rendered the same)
+fn index_entries(entries: &[(String, Node)]) -> HashMap<&str, &Node> {
+    let mut index = HashMap::with_capacity(entries.len());
+    for (key, node) in entries {
+        index.entry(key.as_str()).or_insert(node);
+    }
+    index
+}
+
+fn diff_nodes(
+    old: &Node,
+    new: &Node,
+    path: &mut Vec<String>,
+    options: &DiffOptions,
+    changes: &mut Vec<Change>,
+) {
+    if options.is_ignored(path) {
+        return;
+    }
+    match (old, new) {
+        (Node::Scalar(a), Node::Scalar(b)) => {
+            if a != b {
+                push_change(path, ChangeKind::Modified, Some(old), Some(new), changes);
+            }
+        }
+        (Node::Seq(a), Node::Seq(b)) => {
+            for (idx, (a, b)) in a.iter().zip(b).enumerate() {
+                path.push(idx.to_string());
+                diff_nodes(a, b, path, options, changes);
+                path.pop();
+            }
+            for (idx, b) in b.iter().enumerate().skip(a.len()) {
+                path.push(idx.to_string());
+                if !options.is_ignored(path) {
+                    push_change(path, ChangeKind::Added, None, Some(b), changes);
+                }
+                path.pop();
+            }
+            for (idx, a) in a.iter().enumerate().skip(b.len()).rev() {
+                path.push(idx.to_string());
+                if !options.is_ignored(path) {
+                    push_change(path, ChangeKind::Removed, Some(a), None, changes);
+                }
+                path.pop();
+            }
+        }
+        (Node::Map(a), Node::Map(b)) => {
+            let a_index = index_entries(a);
+            let b_index = index_entries(b);
+            if !options.unordered_maps {
+                let common_a = a.iter().filter(|(k, _)| b_index.contains_key(k.as_str()));
+                let common_b = b.iter().filter(|(k, _)| a_index.contains_key(k.as_str()));
+                if !common_a.map(|(k, _)| k).eq(common_b.map(|(k, _)| k)) {
+                    push_change(path, ChangeKind::Modified, Some(old), Some(new), changes);
+                    return;
+                }
+            }
+            for (key, a) in a {
+                path.push(key.clone());
+                match b_index.get(key.as_str()) {
+                    Some(b) => diff_nodes(a, b, path, options, changes),
+                    None if !options.is_ignored(path) => {
+                        push_change(path, ChangeKind::Removed, Some(a), None, changes);
+                    }
+                    None => {}
+                }
+                path.pop();
+            }
+            for (key, b) in b {
+                if a_index.contains_key(key.as_str()) {
+                    continue;
+                }
+                path.push(key.clone());
+                if !options.is_ignored(path) {
+                    push_change(path, ChangeKind::Added, None, Some(b), changes);
+                }
+                path.pop();
+            }
+        }
+        _ => {
+            push_change(path, ChangeKind::Modified, Some(old), Some(new), changes);
+        }
+    }
+}
+
+#[test]
+fn test_diff() {
+    use std::collections::BTreeMap;
+    #[derive(Serialize)]
+    struct User {
+        name: String,
+        last_login: u64,
+        roles: BTreeMap<String, bool>,
+    }
+    let old = vec![
+        User {
+            name: "alice".to_string(),
+            last_login: 1,
+            roles: [("admin".to_string(), true)].into_iter().collect(),
+        },
+        User {
+            name: "bob".to_string(),
+            last_login: 2,
+            roles: BTreeMap::new(),
+        },
+    ];
+    let new = vec![User {
+        name: "alice".to_string(),
+        last_login: 3,
+        roles: [("dev".to_string(), true)].into_iter().collect(),
+    }];
+    let changes = diff(&old, &new).unwrap();
+    let summary: Vec<(String, ChangeKind)> =
+        changes.iter().map(|c| (c.path.join("."), c.kind)).collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("0.last_login".to_string(), ChangeKind::Modified),
+            ("0.roles.admin".to_string(), ChangeKind::Removed),
+            ("0.roles.dev".to_string(), ChangeKind::Added),
+            ("1".to_string(), ChangeKind::Removed),
+        ]
+    );
+    assert_eq!(changes[0].old, Some(Value::from(1)));
+    assert_eq!(changes[0].new, Some(Value::from(3)));
+
+    let options = DiffOptions::default().ignore("*.last_login").ignore("1");
+    let changes = diff_with_options(&old, &new, &options).unwrap();
+    assert_eq!(changes.len(), 2);
+
+    // maps with a different entry order
+    struct OrderedMap(Vec<(&'static str, i32)>);
+    impl Serialize for OrderedMap {
+        fn serialize<S: serde::Serializer>(
+            &self,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serializer.collect_map(self.0.iter().map(|(k, v)| (k, v)))
+        }
+    }
+    let old = OrderedMap(vec![("a", 1), ("b", 2)]);
+    let new = OrderedMap(vec![("b", 2), ("a", 1)]);
+    assert_eq!(diff(&old, &new).unwrap(), vec![]);
+    let options = DiffOptions::default().unordered_maps(false);
+    let changes = diff_with_options(&old, &new, &options).unwrap();
+    assert_eq!(changes.len(), 1);
+    assert!(changes[0].path.is_empty());
+
+    // non string map keys, and numbers compared without conversion
+    let old: HashMap<(u8, u8), f64> = [((1, 2), 1.0), ((3, 4), 2.0)].into_iter().collect();
+    let new: HashMap<(u8, u8), f64> = [((1, 2), 1.5), ((5, 6), 2.0)].into_iter().collect();
+    let mut changes = diff(&old, &new).unwrap();
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    let summary: Vec<(String, ChangeKind)> =
+        changes.iter().map(|c| (c.path.join("."), c.kind)).collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("[1,2]".to_string(), ChangeKind::Modified),
+            ("[3,4]".to_string(), ChangeKind::Removed),
+            ("[5,6]".to_string(), ChangeKind::Added),
+        ]
+    );
+    let changes = diff(&vec![1u64, u64::MAX], &vec![1.0f64, u64::MAX as f64]).unwrap();
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[0].old, Some(Value::from(1)));
+    assert_eq!(changes[0].new, Some(Value::from(1.0)));
+    assert!(diff(&f64::NAN, &f64::NAN).unwrap().is_empty());
+
+    // enum variants are map keys, as in JSON
+    #[derive(Serialize)]
+    enum Shape {
+        Circle(f64),
+        Square(f64),
+    }
+    let changes = diff(&Shape::Circle(1.0), &Shape::Square(1.0)).unwrap();
+    let summary: Vec<(String, ChangeKind)> =
+        changes.iter().map(|c| (c.path.join("."), c.kind)).collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("Circle".to_string(), ChangeKind::Removed),
+            ("Square".to_string(), ChangeKind::Added),
+        ]
+    );
+}
@@ -1,6 +1,10 @@
 use {
     crate::{
         errors::IqInternalError,
+        map_key::{
+            MapKey,
+            PathKey,
+        },
         *,
     },
     serde::{
@@ -10,8 +14,8 @@ use {
 };
 
 /// The thing wich dives into a Serialize value and goes directly
-/// to the searched value.
-pub(crate) struct Diver<'p> {
+/// to the searched value, then gives it to the formatter.
+pub(crate) struct Diver<'p, F: IqFormatter> {
     keys: &'p [&'p str],
     next_token: usize,
     requested_seq_idx: usize,
@@ -19,12 +23,18 @@ pub(crate) struct Diver<'p> {
     return_next_primitive: bool,
     accept_next_map_value: bool,
     return_next_map_value: bool,
-    format: IqFormat,
+    formatter: F,
+    found: Option<F::Output>,
+    key_matching: KeyMatching,
+    map_key: MapKey,
+    /// the key of the path compared with the keys of the current map,
+    /// with its index in the path (it's parsed once for all the entries)
+    path_key: Option<(usize, PathKey)>,
 }
-impl<'p> Diver<'p> {
+impl<'p, F: IqFormatter> Diver<'p, F> {
     pub fn new(
         keys: &'p [&'p str],
-        format: IqFormat,
+        formatter: F,
     ) -> Self {
         Self {
             keys,
@@ -34,17 +44,44 @@ impl<'p> Diver<'p> {
             return_next_primitive: false,
             return_next_map_value: false,
             accept_next_map_value: false,
-            format,
+            formatter,
+            found: None,
+            key_matching: KeyMatching::default(),
+            map_key: MapKey::default(),
+            path_key: None,
         }
     }
-    pub fn set_return_next_primitive(&mut self) {
-        self.return_next_primitive = true;
+    pub fn set_key_matching(
+        &mut self,
+        key_matching: KeyMatching,
+    ) {
+        self.key_matching = key_matching;
+    }
+    /// Go to the value at the path and return it, formatted.
+    ///
+    /// An empty path designates the whole value.
+    pub fn dive<T: ?Sized + Serialize>(
+        mut self,
+        source: &T,
+    ) -> Result<Option<F::Output>, IqError> {
+        let res = if self.keys.is_empty() {
+            self.on_found_with_value(source)
+        } else {
+            source.serialize(&mut self)
+        };
+        match res {
+            Err(IqInternalError::Done) => Ok(self.found),
+            Err(IqInternalError::Message(msg)) => Err(IqError::Serde(msg)),
+            Err(IqInternalError::Json(err)) => Err(IqError::Json(err)),
+            _ => Ok(None), // not found, or path not matching the value
+        }
     }
     fn has_next_token(
         &self,
         key: &str,
     ) -> bool {
-        self.next_token < self.keys.len() && key == self.keys[self.next_token]
+        self.next_token < self.keys.len()
+            && self.key_matching.matches(self.keys[self.next_token], key)
     }
     fn on_found_with_value<T>(
         &mut self,
@@ -53,24 +90,9 @@ impl<'p> Diver<'p> {
     where
         T: ?Sized + Serialize,
     {
-        match self.format {
-            IqFormat::Primitive => {
-                self.return_next_primitive = true;
-                Ok(())
-            }
-            IqFormat::Json => {
-                let json = serde_json::to_string(value)?;
-                Err(IqInternalError::Found(json))
-            }
-            IqFormat::JsonPretty => {
-                let json = serde_json::to_string_pretty(value)?;
-                Err(IqInternalError::Found(json))
-            }
-            IqFormat::Size => match Sizer::count(value) {
-                Some(count) => Err(IqInternalError::Count(count)),
-                None => Err(IqInternalError::NoCount),
-            },
-        }
+        let value: &dyn erased_serde::Serialize = &value;
+        self.found = self.formatter.format(value)?;
+        Err(IqInternalError::Done)
     }
     fn incr_next_token_with_value<T>(
         &mut self,
@@ -86,7 +108,22 @@ impl<'p> Diver<'p> {
         Ok(())
     }
 }
-impl ser::Serializer for &mut Diver<'_> {
+
+/// Render a value as a string, if it's a "primitive" (including
+/// strings, simple enum variants, etc), using its `Display` implementation
+pub(crate) fn render_primitive<T: ?Sized + Serialize>(
+    value: &T
+) -> Result<Option<String>, IqError> {
+    let mut diver = Diver::new(&[], IqFormat::Primitive);
+    diver.return_next_primitive = true;
+    match value.serialize(&mut diver) {
+        Err(IqInternalError::Found(s)) => Ok(Some(s)),
+        Err(IqInternalError::Message(msg)) => Err(IqError::Serde(msg)),
+        Err(IqInternalError::Json(err)) => Err(IqError::Json(err)),
+        _ => Ok(None), // not a primitive
+    }
+}
+impl<F: IqFormatter> ser::Serializer for &mut Diver<'_, F> {
     type Ok = ();
     type Error = IqInternalError;
     type SerializeSeq = Self;
@@ -317,10 +354,10 @@ impl ser::Serializer for &mut Diver<'_> {
         _name: &'static str,
         _variant_index: u32,
         variant: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeTupleVariant, IqInternalError> {
         variant.serialize(&mut *self)?;
-        Ok(self)
+        self.serialize_seq(Some(len))
     }
 
     fn serialize_map(
@@ -349,7 +386,7 @@ impl ser::Serializer for &mut Diver<'_> {
         Ok(self)
     }
 }
-impl ser::SerializeSeq for &mut Diver<'_> {
+impl<F: IqFormatter> ser::SerializeSeq for &mut Diver<'_, F> {
     type Ok = ();
     type Error = IqInternalError;
     fn serialize_element<T>(
@@ -371,7 +408,7 @@ impl ser::SerializeSeq for &mut Diver<'_> {
     }
 }
 
-impl ser::SerializeTuple for &mut Diver<'_> {
+impl<F: IqFormatter> ser::SerializeTuple for &mut Diver<'_, F> {
     type Ok = ();
     type Error = IqInternalError;
     fn serialize_element<T>(
@@ -393,7 +430,7 @@ impl ser::SerializeTuple for &mut Diver<'_> {
     }
 }
 
-impl ser::SerializeTupleStruct for &mut Diver<'_> {
+impl<F: IqFormatter> ser::SerializeTupleStruct for &mut Diver<'_, F> {
     type Ok = ();
     type Error = IqInternalError;
 
@@ -417,8 +454,7 @@ impl ser::SerializeTupleStruct for &mut Diver<'_> {
     }
 }
 
-// TODO not sure I correctly handled this thing
-impl ser::SerializeTupleVariant for &mut Diver<'_> {
+impl<F: IqFormatter> ser::SerializeTupleVariant for &mut Diver<'_, F> {
     type Ok = ();
     type Error = IqInternalError;
 
@@ -429,7 +465,12 @@ impl ser::SerializeTupleVariant for &mut Diver<'_> {
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(&mut **self)
+        if self.current_seq_idx == self.requested_seq_idx {
+            self.incr_next_token_with_value(value)?;
+            value.serialize(&mut **self)?;
+        }
+        self.current_seq_idx += 1;
+        Ok(())
     }
 
     fn end(self) -> Result<(), IqInternalError> {
@@ -437,22 +478,32 @@ impl ser::SerializeTupleVariant for &mut Diver<'_> {
     }
 }
 
-impl ser::SerializeMap for &mut Diver<'_> {
+impl<F: IqFormatter> ser::SerializeMap for &mut Diver<'_, F> {
     type Ok = ();
     type Error = IqInternalError;
     fn serialize_key<T>(
         &mut self,
-        _key: &T,
+        key: &T,
     ) -> Result<(), IqInternalError>
     where
         T: ?Sized + Serialize,
     {
         // The key can be anything. For the purpose of comparing with the path,
-        // we'll take the JSON representation of the key, with quotes removed.
-        // For complex composite keys, a specific query language might be needed.
-        let key = serde_json::to_string(_key)?;
-        self.accept_next_map_value =
-            key.trim_matches('"') == self.keys[self.next_token].trim_matches('"');
+        // it's rendered (see MapKey), and compared with the parsed path key.
+        let token = self.next_token;
+        if token >= self.keys.len() {
+            // we're past the searched path (eg looking for a primitive in a map)
+            self.accept_next_map_value = false;
+            return Ok(());
+        }
+        if !matches!(&self.path_key, Some((t, _)) if *t == token) {
+            self.path_key = Some((token, PathKey::new(self.keys[token])));
+        }
+        self.map_key.render(key)?;
+        self.accept_next_map_value = match &self.path_key {
+            Some((_, path_key)) => self.map_key.matches(path_key, self.key_matching),
+            None => false,
+        };
         if self.accept_next_map_value {
             self.next_token += 1;
             if self.next_token >= self.keys.len() {
@@ -481,7 +532,7 @@ impl ser::SerializeMap for &mut Diver<'_> {
     }
 }
 
-impl ser::SerializeStruct for &mut Diver<'_> {
+impl<F: IqFormatter> ser::SerializeStruct for &mut Diver<'_, F> {
     type Ok = ();
     type Error = IqInternalError;
     fn serialize_field<T>(
@@ -505,7 +556,7 @@ impl ser::SerializeStruct for &mut Diver<'_> {
 
 // Similar to `SerializeTupleVariant`, here the `end` method is responsible for
 // closing both of the curly braces opened by `serialize_struct_variant`.
-impl ser::SerializeStructVariant for &mut Diver<'_> {
+impl<F: IqFormatter> ser::SerializeStructVariant for &mut Diver<'_, F> {
     type Ok = ();
     type Error = IqInternalError;
     fn serialize_field<T>(
@@ -14,6 +14,10 @@ pub(crate) enum IqInternalError {
     OutOfBounds,
     Count(usize),
     NoCount,
+    /// The walk was stopped because there's nothing more to search
+    Done,
+    /// The map key can't be rendered as a string
+    NotAKey,
 }
 impl std::error::Error for IqInternalError {}
 impl ser::Error for IqInternalError {
@@ -26,6 +30,14 @@ impl From<serde_json::Error> for IqInternalError {
         Self::Json(err)
     }
 }
+impl From<IqError> for IqInternalError {
+    fn from(err: IqError) -> Self {
+        match err {
+            IqError::Json(err) => Self::Json(err),
+            err => Self::Message(err.to_string()),
+        }
+    }
+}
 impl fmt::Display for IqInternalError {
     fn fmt(
         &self,
@@ -39,6 +51,8 @@ impl fmt::Display for IqInternalError {
             Self::Found(_) => write!(formatter, "IQ Error: Found"),
             Self::Count(count) => write!(formatter, "IQ Error: Count: {}", count),
             Self::NoCount => write!(formatter, "IQ Error: No Count"),
+            Self::Done => write!(formatter, "IQ Error: Done"),
+            Self::NotAKey => write!(formatter, "IQ Error: Not a key"),
         }
     }
 }
@@ -48,6 +62,10 @@ impl fmt::Display for IqInternalError {
 pub enum IqError {
     Serde(String),
     Json(serde_json::Error),
+    /// A change was attempted at a path which isn't allowed
+    Forbidden(String),
+    /// A value doesn't survive a JSON round trip, at the given path
+    Lossy(String),
 }
 impl std::error::Error for IqError {}
 impl From<serde_json::Error> for IqError {
@@ -63,6 +81,8 @@ impl fmt::Display for IqError {
         match self {
             Self::Serde(msg) => write!(formatter, "Serde Error: {}", msg),
             Self::Json(err) => write!(formatter, "JSON error: {}", err),
+            Self::Forbidden(path) => write!(formatter, "Forbidden path: {:?}", path),
+            Self::Lossy(path) => write!(formatter, "Value lost in JSON at {:?}", path),
         }
     }
 }
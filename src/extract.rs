@@ -2,18 +2,29 @@ use {
     crate::{
         diver::Diver,
         errors::IqInternalError,
+        formatter::{
+            JsonWriter,
+            SizeCounter,
+        },
+        pattern::PatternTree,
+        walker::*,
         *,
     },
     serde::{
         Serialize,
         de::DeserializeOwned,
     },
+    std::{
+        io,
+        ops::ControlFlow,
+    },
 };
 
 /// Format for the extracted value
 ///
 /// Warning: this enum is expected to change. Prefer to use the public
-/// functions to extract values.
+/// functions to extract values, or to implement [IqFormatter] for
+/// a format of your own.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum IqFormat {
     /// Exctract as Display, but only if the value is a "primitive"
@@ -37,38 +48,36 @@ pub fn extract_string_checked<T: Serialize, P: IqPath>(
     path: P,
     format: IqFormat,
 ) -> Result<Option<String>, IqError> {
+    extract_with_options(source, path, format, &IqOptions::default())
+}
+
+/// Extract a string from a structure at a given path, with a given format
+/// and options (eg a case-insensitive matching of keys).
+///
+/// If the path is not found, return None.
+pub fn extract_with_options<T: Serialize, P: IqPath>(
+    source: &T,
+    path: P,
+    format: IqFormat,
+    options: &IqOptions,
+) -> Result<Option<String>, IqError> {
+    extract_formatted(source, path, format, options)
+}
+
+/// Extract the value at a given path, rendered by a formatter.
+///
+/// If the path is not found, or if the formatter can't render the
+/// value, return None. An empty path designates the whole value.
+pub fn extract_formatted<T: Serialize, P: IqPath, F: IqFormatter>(
+    source: &T,
+    path: P,
+    formatter: F,
+    options: &IqOptions,
+) -> Result<Option<F::Output>, IqError> {
     let keys: Vec<&str> = path.keys().collect();
-    let mut diver = Diver::new(&keys, format);
-    if keys.is_empty() {
-        match format {
-            IqFormat::Primitive => {
-                diver.set_return_next_primitive();
-            }
-            IqFormat::Json => {
-                return serde_json::to_string(source)
-                    .map_err(IqError::Json)
-                    .map(Some);
-            }
-            IqFormat::JsonPretty => {
-                return serde_json::to_string_pretty(source)
-                    .map_err(IqError::Json)
-                    .map(Some);
-            }
-            IqFormat::Size => {
-                return Ok(Sizer::count(source).map(|n| n.to_string()));
-            }
-        }
-    }
-    match source.serialize(&mut diver) {
-        Ok(()) => Ok(None), // Not found
-        Err(IqInternalError::Found(json)) => Ok(Some(json)),
-        Err(IqInternalError::Message(msg)) => Err(IqError::Serde(msg)),
-        Err(IqInternalError::Json(err)) => Err(IqError::Json(err)),
-        Err(IqInternalError::IndexExpected) => Ok(None), // path doesn't match
-        Err(IqInternalError::OutOfBounds) => Ok(None),   // path doesn't match
-        Err(IqInternalError::Count(n)) => Ok(Some(n.to_string())),
-        Err(IqInternalError::NoCount) => Ok(None),
-    }
+    let mut diver = Diver::new(&keys, formatter);
+    diver.set_key_matching(options.key_matching);
+    diver.dive(source)
 }
 /// Extract a string from a structure at a given path, with a given format.
 ///
@@ -100,6 +109,50 @@ pub fn extract_json_pretty<T: Serialize, P: IqPath>(
     extract_string(source, path, IqFormat::JsonPretty)
 }
 
+/// Write the value at the given path as JSON into a writer (eg a file,
+/// or the body of an HTTP response), without building the JSON string.
+///
+/// Return `Ok(false)` if the path isn't found, in which case nothing
+/// is written. The writer isn't flushed.
+///
+/// ```
+/// let data = (("a", 'b'), [1, 2, 3]);
+/// let mut out = Vec::new();
+/// assert!(iq::extract_json_to_writer(&data, "1", &mut out).unwrap());
+/// assert_eq!(out, b"[1,2,3]");
+/// assert!(!iq::extract_json_to_writer(&data, "2", &mut out).unwrap());
+/// ```
+pub fn extract_json_to_writer<T: Serialize, P: IqPath, W: io::Write>(
+    source: &T,
+    path: P,
+    writer: W,
+) -> Result<bool, IqError> {
+    write_json(source, path, writer, false)
+}
+
+/// Write the value at the given path as pretty JSON into a writer.
+///
+/// Return `Ok(false)` if the path isn't found, in which case nothing
+/// is written.
+pub fn extract_json_pretty_to_writer<T: Serialize, P: IqPath, W: io::Write>(
+    source: &T,
+    path: P,
+    writer: W,
+) -> Result<bool, IqError> {
+    write_json(source, path, writer, true)
+}
+
+fn write_json<T: Serialize, P: IqPath, W: io::Write>(
+    source: &T,
+    path: P,
+    writer: W,
+    pretty: bool,
+) -> Result<bool, IqError> {
+    let formatter = JsonWriter { writer, pretty };
+    let written = extract_formatted(source, path, formatter, &IqOptions::default())?;
+    Ok(written.is_some())
+}
+
 /// Extract a "primitive" value (including strings, simple enum variants, etc)
 /// as a string using the `Display` implementation of the deep value.
 pub fn extract_primitive<T: Serialize, P: IqPath>(
@@ -128,16 +181,197 @@ pub fn extract_value<T: Serialize, P: IqPath, V: DeserializeOwned>(
 pub fn extract_size<T: Serialize, P: IqPath>(
     source: &T,
     path: P,
+) -> Option<usize> {
+    extract_size_with_options(source, path, &IqOptions::default())
+}
+
+/// Extract a "primitive" value with the given options.
+///
+/// This function also returns None if the the `Serialize` implementation fails.
+pub fn extract_primitive_with_options<T: Serialize, P: IqPath>(
+    source: &T,
+    path: P,
+    options: &IqOptions,
+) -> Option<String> {
+    extract_with_options(source, path, IqFormat::Primitive, options).unwrap_or(None)
+}
+
+/// Extract a value as JSON with the given options.
+///
+/// This function also returns None if the the `Serialize` implementation fails.
+pub fn extract_json_with_options<T: Serialize, P: IqPath>(
+    source: &T,
+    path: P,
+    options: &IqOptions,
+) -> Option<String> {
+    extract_with_options(source, path, IqFormat::Json, options).unwrap_or(None)
+}
+
+/// Extract a value, which must implement `Deserialize`, with the given options
+pub fn extract_value_with_options<T: Serialize, P: IqPath, V: DeserializeOwned>(
+    source: &T,
+    path: P,
+    options: &IqOptions,
+) -> Result<Option<V>, IqError> {
+    let json = extract_with_options(source, path, IqFormat::Json, options)?;
+    let value = json.map(|json| serde_json::from_str(&json)).transpose()?;
+    Ok(value)
+}
+
+/// Extract the size of the array/map/struct/tupple/string at the end of
+/// the path, with the given options
+pub fn extract_size_with_options<T: Serialize, P: IqPath>(
+    source: &T,
+    path: P,
+    options: &IqOptions,
 ) -> Option<usize> {
     let keys: Vec<&str> = path.keys().collect();
     if keys.first().map_or(true, |s| s.is_empty()) {
         return Sizer::count(source);
     }
-    let mut diver = Diver::new(&keys, IqFormat::Size);
-    match source.serialize(&mut diver) {
-        Err(IqInternalError::Count(n)) => Some(n),
-        Err(IqInternalError::NoCount) => None, // not countable
-        _ => None,                             // not found, or object not serializable
+    let mut diver = Diver::new(&keys, SizeCounter);
+    diver.set_key_matching(options.key_matching);
+    diver.dive(source).unwrap_or(None) // None if not found or not serializable
+}
+
+/// Extract strings from a structure at several paths, with a given format.
+///
+/// The structure is visited only once, and the visit stops as soon as all
+/// paths are resolved.
+///
+/// The returned vec has the same length than `paths`, with None for the
+/// paths which weren't found. An empty path designates the whole value.
+///
+/// If you extract the same paths from many values, build a [QuerySet] once
+/// instead.
+pub fn extract_many_checked<T: Serialize, P: IqPath>(
+    source: &T,
+    paths: &[P],
+    format: IqFormat,
+) -> Result<Vec<Option<String>>, IqError> {
+    QuerySet::new(paths).extract_checked(source, format)
+}
+
+/// Extract strings from a structure at several paths, with a given format.
+///
+/// This function returns only Nones if the `Serialize` implementation fails,
+/// which should not happen with a standard implementation.
+pub fn extract_many_strings<T: Serialize, P: IqPath>(
+    source: &T,
+    paths: &[P],
+    format: IqFormat,
+) -> Vec<Option<String>> {
+    extract_many_checked(source, paths, format).unwrap_or_else(|_| vec![None; paths.len()])
+}
+
+/// Extract "primitive" values at several paths, in one visit of the value.
+///
+/// ```
+/// let data = (("a", 'b'), [1, 2, 3]);
+/// assert_eq!(
+///     iq::extract_many(&data, &["1.2", "0.0", "1.5", "1"]),
+///     vec![Some("3".to_string()), Some("a".to_string()), None, None],
+/// );
+/// ```
+pub fn extract_many<T: Serialize, P: IqPath>(
+    source: &T,
+    paths: &[P],
+) -> Vec<Option<String>> {
+    extract_many_strings(source, paths, IqFormat::Primitive)
+}
+
+/// Extract values as JSON at several paths, in one visit of the value
+pub fn extract_many_json<T: Serialize, P: IqPath>(
+    source: &T,
+    paths: &[P],
+) -> Vec<Option<String>> {
+    extract_many_strings(source, paths, IqFormat::Json)
+}
+
+/// Extract strings at all the paths matching a pattern, with a given format.
+///
+/// In the pattern, a `*` key matches any key or index, a key with `*` or `?`
+/// is a glob (eg `cpu_*`), and, with the `regex` feature, a key between
+/// slashes is a regular expression (eg `/^cpu_\d+$/`).
+///
+/// Return the concrete paths and the extracted strings, in the order of
+/// the visit. Values which can't be extracted in the format (eg non
+/// primitive values with `IqFormat::Primitive`) are skipped.
+pub fn extract_all_checked<T: Serialize, P: IqPath>(
+    source: &T,
+    pattern: P,
+    format: IqFormat,
+) -> Result<Vec<(Vec<String>, String)>, IqError> {
+    let mut tree = PatternTree::new();
+    tree.add(&pattern);
+    let gatherer = Gatherer {
+        format,
+        found: Vec::new(),
+    };
+    match Walker::new(&tree, gatherer).walk(source) {
+        Ok(gatherer) => Ok(gatherer.found),
+        Err(IqInternalError::Message(msg)) => Err(IqError::Serde(msg)),
+        Err(IqInternalError::Json(err)) => Err(IqError::Json(err)),
+        Err(_) => Ok(Vec::new()), // not expected
+    }
+}
+
+/// Extract "primitive" values at all the paths matching a pattern.
+///
+/// This function returns an empty vec if the `Serialize` implementation fails,
+/// which should not happen with a standard implementation.
+///
+/// ```
+/// use std::collections::BTreeMap;
+///
+/// let metrics: BTreeMap<&str, f32> = [("cpu_0", 0.5), ("cpu_1", 0.25), ("mem", 0.75)]
+///     .into_iter()
+///     .collect();
+/// let loads = iq::extract_all(&metrics, "cpu_*");
+/// assert_eq!(
+///     loads,
+///     vec![
+///         (vec!["cpu_0".to_string()], "0.5".to_string()),
+///         (vec!["cpu_1".to_string()], "0.25".to_string()),
+///     ],
+/// );
+/// ```
+pub fn extract_all<T: Serialize, P: IqPath>(
+    source: &T,
+    pattern: P,
+) -> Vec<(Vec<String>, String)> {
+    extract_all_checked(source, pattern, IqFormat::Primitive).unwrap_or_default()
+}
+
+/// Extract values as JSON at all the paths matching a pattern.
+///
+/// This function returns an empty vec if the `Serialize` implementation fails.
+pub fn extract_all_json<T: Serialize, P: IqPath>(
+    source: &T,
+    pattern: P,
+) -> Vec<(Vec<String>, String)> {
+    extract_all_checked(source, pattern, IqFormat::Json).unwrap_or_default()
+}
+
+/// The visitor of `extract_all_checked`, rendering all matching values
+struct Gatherer {
+    format: IqFormat,
+    found: Vec<(Vec<String>, String)>,
+}
+impl Visit for Gatherer {
+    fn visit<T>(
+        &mut self,
+        _node: usize,
+        path: &[String],
+        value: &T,
+    ) -> Result<ControlFlow<()>, IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        if let Some(s) = extract_string_checked(&value, Vec::new(), self.format)? {
+            self.found.push((path.to_vec(), s));
+        }
+        Ok(ControlFlow::Continue(()))
     }
 }
 
@@ -168,6 +402,168 @@ fn test_extract_size() {
     assert_eq!(extract_size(&thing, vec![]).unwrap(), 3);
 }
 
+#[test]
+fn test_extract_json_to_writer() {
+    use std::collections::BTreeMap;
+    let data: BTreeMap<&str, Vec<(u8, &str)>> =
+        [("rows", vec![(1, "a"), (2, "b")]), ("empty", vec![])]
+            .into_iter()
+            .collect();
+    let mut out = Vec::new();
+    assert!(extract_json_to_writer(&data, "rows.1", &mut out).unwrap());
+    assert_eq!(String::from_utf8(out).unwrap(), r#"[2,"b"]"#);
+    let mut out = Vec::new();
+    assert!(extract_json_pretty_to_writer(&data, "empty", &mut out).unwrap());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        extract_json_pretty(&data, "empty").unwrap(),
+    );
+    let mut out = Vec::new();
+    assert!(extract_json_to_writer(&data, Vec::<&str>::new(), &mut out).unwrap());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        extract_json(&data, Vec::<&str>::new()).unwrap()
+    );
+    let mut out = Vec::new();
+    assert!(!extract_json_to_writer(&data, "rows.2", &mut out).unwrap());
+    assert!(!extract_json_to_writer(&data, "cols", &mut out).unwrap());
+    assert!(out.is_empty());
+    // errors of the writer are reported
+    struct Failing;
+    impl io::Write for Failing {
+        fn write(
+            &mut self,
+            _: &[u8],
+        ) -> io::Result<usize> {
+            Err(io::Error::other("disk full"))
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+    assert!(matches!(
+        extract_json_to_writer(&data, "rows", Failing),
+        Err(IqError::Json(_)),
+    ));
+}
+
+#[test]
+fn test_extract_many() {
+    #[derive(Serialize)]
+    struct Dog {
+        name: &'static str,
+        ears: u8,
+        tags: std::collections::BTreeMap<&'static str, Option<i32>>,
+    }
+    let dogs = vec![
+        Dog {
+            name: "Rex",
+            ears: 2,
+            tags: [("age", Some(5)), ("weight", None)].into_iter().collect(),
+        },
+        Dog {
+            name: "Laïka",
+            ears: 2,
+            tags: Default::default(),
+        },
+    ];
+    let paths = [
+        "1.name",
+        "0.tags.age",
+        "0.tags.weight",
+        "0.tags",
+        "0.tags.color",
+        "1.name",
+        "2",
+    ];
+    assert_eq!(
+        extract_many(&dogs, &paths),
+        paths
+            .iter()
+            .map(|path| extract_primitive(&dogs, *path))
+            .collect::<Vec<_>>(),
+    );
+    assert_eq!(
+        extract_many_json(&dogs, &paths),
+        paths
+            .iter()
+            .map(|path| extract_json(&dogs, *path))
+            .collect::<Vec<_>>(),
+    );
+    assert_eq!(
+        extract_many(&dogs, &[] as &[&str]),
+        Vec::<Option<String>>::new()
+    );
+    // keys are literal (no pattern), and enum variants are handled
+    // as by extract_primitive
+    #[derive(Serialize)]
+    enum Shape {
+        Circle { x: i32, radius: u32 },
+        Dot(i32, i32),
+        Id(u8),
+        Unit,
+    }
+    #[derive(Serialize)]
+    struct Drawing {
+        keys: std::collections::BTreeMap<&'static str, u8>,
+        shapes: Vec<Shape>,
+        origins: std::collections::HashMap<(u8, u8), &'static str>,
+    }
+    let drawing = Drawing {
+        keys: [
+            ("a", 1),
+            ("a?", 2),
+            ("b*", 3),
+            ("*", 4),
+            ("/^a/", 5),
+            ("08", 6),
+        ]
+        .into_iter()
+        .collect(),
+        shapes: vec![
+            Shape::Circle { x: 1, radius: 2 },
+            Shape::Dot(3, 4),
+            Shape::Id(5),
+            Shape::Unit,
+        ],
+        origins: [((3, 4), "here")].into_iter().collect(),
+    };
+    let paths = [
+        "keys.a?",
+        "keys.b*",
+        "keys.*",
+        "keys./^a/",
+        "keys.ab",
+        "keys.08",
+        "keys.8",
+        "shapes.*",
+        "shapes.0.x",
+        "shapes.0.Circle",
+        "shapes.1",
+        "shapes.1.1",
+        "shapes.1.2",
+        "shapes.2",
+        "shapes.2.0",
+        "shapes.3",
+        "origins.(3,4)",
+        "origins.(3, 4)",
+    ];
+    let many = extract_many(&drawing, &paths);
+    for (path, extracted) in paths.iter().zip(&many) {
+        assert_eq!(
+            extracted,
+            &extract_primitive(&drawing, *path),
+            "path: {}",
+            path
+        );
+    }
+    assert_eq!(many[0].as_deref(), Some("2"));
+    assert_eq!(many[2].as_deref(), Some("4"));
+    assert_eq!(many[4], None);
+    assert_eq!(many[7], None);
+    assert_eq!(many[11].as_deref(), Some("4"));
+}
+
 #[test]
 fn test_extract_value_on_empty_path() {
     #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
@@ -182,3 +578,130 @@ fn test_extract_value_on_empty_path() {
     let extracted = extract_value(&apple, vec![]).unwrap();
     assert_eq!(extracted, Some(apple));
 }
+
+#[test]
+fn test_extract_with_options() {
+    use std::collections::HashMap;
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Server {
+        host_name: &'static str,
+        open_ports: Vec<u16>,
+        limits: HashMap<&'static str, u32>,
+    }
+    let server = Server {
+        host_name: "alpha",
+        open_ports: vec![22, 443],
+        limits: [("max-connections", 100)].into_iter().collect(),
+    };
+    let exact = IqOptions::new();
+    // map keys are compared as is, quotes included
+    let quoted: HashMap<&str, u8> = [("\"a\"", 1), ("b", 2)].into_iter().collect();
+    assert_eq!(extract_primitive(&quoted, "\"a\""), Some("1".to_string()));
+    assert_eq!(extract_primitive(&quoted, "a"), None);
+    assert_eq!(extract_primitive(&quoted, "\"b\""), None);
+    let case = IqOptions::new().with_key_matching(KeyMatching::AsciiCaseInsensitive);
+    let style = IqOptions::new().with_key_matching(KeyMatching::StyleInsensitive);
+    assert_eq!(
+        extract_primitive_with_options(&server, "hostName", &exact),
+        Some("alpha".to_string()),
+    );
+    assert_eq!(
+        extract_primitive_with_options(&server, "hostname", &exact),
+        None
+    );
+    assert_eq!(
+        extract_primitive_with_options(&server, "HOSTNAME", &case),
+        Some("alpha".to_string()),
+    );
+    assert_eq!(
+        extract_primitive_with_options(&server, "host_name", &case),
+        None
+    );
+    assert_eq!(
+        extract_primitive_with_options(&server, "host_name", &style),
+        Some("alpha".to_string()),
+    );
+    assert_eq!(
+        extract_json_with_options(&server, "open-ports", &style),
+        Some("[22,443]".to_string()),
+    );
+    assert_eq!(
+        extract_size_with_options(&server, "OPEN_PORTS", &style),
+        Some(2)
+    );
+    assert_eq!(
+        extract_value_with_options(&server, "limits.MaxConnections", &style).unwrap(),
+        Some(100),
+    );
+    assert_eq!(
+        extract_value_with_options::<_, _, u32>(&server, "limits.MaxConnections", &case).unwrap(),
+        None,
+    );
+}
+
+#[test]
+fn test_extract_all() {
+    use std::collections::BTreeMap;
+    #[derive(Serialize)]
+    struct Host {
+        name: &'static str,
+        metrics: BTreeMap<&'static str, u32>,
+    }
+    let hosts = vec![
+        Host {
+            name: "alpha",
+            metrics: [("cpu_0", 10), ("cpu_1", 20), ("cpu_total", 30), ("mem", 40)]
+                .into_iter()
+                .collect(),
+        },
+        Host {
+            name: "beta",
+            metrics: [("cpu_0", 50), ("gpu_0", 60)].into_iter().collect(),
+        },
+    ];
+    let all = |pattern: &str| -> Vec<(String, String)> {
+        extract_all(&hosts, pattern)
+            .into_iter()
+            .map(|(path, value)| (path.join("."), value))
+            .collect()
+    };
+    let pair = |path: &str, value: &str| (path.to_string(), value.to_string());
+    assert_eq!(
+        all("*.name"),
+        vec![pair("0.name", "alpha"), pair("1.name", "beta")]
+    );
+    assert_eq!(
+        all("*.metrics.cpu_*"),
+        vec![
+            pair("0.metrics.cpu_0", "10"),
+            pair("0.metrics.cpu_1", "20"),
+            pair("0.metrics.cpu_total", "30"),
+            pair("1.metrics.cpu_0", "50"),
+        ],
+    );
+    assert_eq!(
+        all("*.metrics.?pu_0"),
+        vec![
+            pair("0.metrics.cpu_0", "10"),
+            pair("1.metrics.cpu_0", "50"),
+            pair("1.metrics.gpu_0", "60"),
+        ],
+    );
+    assert_eq!(all("1.m*"), vec![]); // not a primitive
+    assert_eq!(
+        extract_all_json(&hosts, "1.m*"),
+        vec![(
+            vec!["1".to_string(), "metrics".to_string()],
+            r#"{"cpu_0":50,"gpu_0":60}"#.to_string(),
+        )],
+    );
+    #[cfg(feature = "regex")]
+    {
+        assert_eq!(
+            all(r"0.metrics./^cpu_\d+$/"),
+            vec![pair("0.metrics.cpu_0", "10"), pair("0.metrics.cpu_1", "20")],
+        );
+        assert_eq!(all(r"/^[1-9]$/.name"), vec![pair("1.name", "beta")]);
+    }
+}
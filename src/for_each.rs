@@ -0,0 +1,311 @@
+use {
+    crate::{
+        errors::IqInternalError,
+        pattern::PatternTree,
+        walker::*,
+        *,
+    },
+    serde::{
+        Serialize,
+        de::DeserializeOwned,
+    },
+    std::ops::ControlFlow,
+};
+
+/// A value found during a visit, which can be rendered or deserialized
+/// on demand.
+///
+/// It's only valid during the call of the callback it's given to.
+#[derive(Clone, Copy)]
+pub struct IqNode<'n> {
+    value: &'n dyn Render,
+}
+
+/// The operations an `IqNode` can do on its value, whatever its type
+trait Render {
+    fn extract(
+        &self,
+        format: IqFormat,
+    ) -> Result<Option<String>, IqError>;
+    fn to_json_value(&self) -> Result<serde_json::Value, serde_json::Error>;
+    fn as_serialize(&self) -> &dyn erased_serde::Serialize;
+}
+
+/// A sized wrapper, so that any serializable value can be a `dyn Render`
+struct Wrapped<'v, T: ?Sized>(&'v T);
+
+impl<T: ?Sized + Serialize> Render for Wrapped<'_, T> {
+    fn extract(
+        &self,
+        format: IqFormat,
+    ) -> Result<Option<String>, IqError> {
+        extract_string_checked(&self.0, Vec::<&str>::new(), format)
+    }
+    fn to_json_value(&self) -> Result<serde_json::Value, serde_json::Error> {
+        serde_json::to_value(self.0)
+    }
+    fn as_serialize(&self) -> &dyn erased_serde::Serialize {
+        &self.0
+    }
+}
+
+/// A node serializes as its value, so that it can be the source of
+/// another extraction
+impl Serialize for IqNode<'_> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        erased_serde::serialize(self.value.as_serialize(), serializer)
+    }
+}
+
+impl IqNode<'_> {
+    /// Render the value with the given format
+    pub fn extract(
+        &self,
+        format: IqFormat,
+    ) -> Result<Option<String>, IqError> {
+        self.value.extract(format)
+    }
+    /// Render the value as a string, if it's a "primitive"
+    pub fn primitive(&self) -> Option<String> {
+        self.extract(IqFormat::Primitive).unwrap_or(None)
+    }
+    /// Render the value as JSON
+    pub fn json(&self) -> Option<String> {
+        self.extract(IqFormat::Json).unwrap_or(None)
+    }
+    /// Render the value as pretty JSON
+    pub fn json_pretty(&self) -> Option<String> {
+        self.extract(IqFormat::JsonPretty).unwrap_or(None)
+    }
+    /// Return the size of the array/map/struct/tupple/string
+    pub fn size(&self) -> Option<usize> {
+        self.extract(IqFormat::Size)
+            .unwrap_or(None)
+            .and_then(|size| size.parse().ok())
+    }
+    /// Deserialize the value into any type implementing `Deserialize`,
+    /// with a JSON value as intermediate step
+    pub fn deserialize<V: DeserializeOwned>(&self) -> Result<V, IqError> {
+        let value = self.value.to_json_value()?;
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+/// Call the callback for every value matching the pattern, in the order
+/// of the visit, with its concrete path, until the callback returns
+/// `ControlFlow::Break`.
+///
+/// Nothing is collected, and the values are rendered only if the
+/// callback asks for it.
+///
+/// The pattern may contain `*` keys, globs, and, with the `regex` feature,
+/// regular expressions (see [IqPath]).
+///
+/// ```
+/// use std::ops::ControlFlow;
+///
+/// let scores = vec![("alice", 12), ("bob", 45), ("carol", 78), ("dave", 3)];
+/// let mut first_high = None;
+/// iq::for_each(&scores, "*.1", |path, node| {
+///     if node.deserialize::<u32>().unwrap() > 40 {
+///         first_high = Some(path[0].clone());
+///         return ControlFlow::Break(());
+///     }
+///     ControlFlow::Continue(())
+/// })
+/// .unwrap();
+/// assert_eq!(first_high.as_deref(), Some("1"));
+/// ```
+pub fn for_each<T, P, F>(
+    source: &T,
+    pattern: P,
+    f: F,
+) -> Result<(), IqError>
+where
+    T: ?Sized + Serialize,
+    P: IqPath,
+    F: FnMut(&[String], IqNode<'_>) -> ControlFlow<()>,
+{
+    let mut tree = PatternTree::new();
+    tree.add(&pattern);
+    walk_with(&tree, source, f)
+}
+
+/// Call the callback for every child (field, item, map entry) of the
+/// value at the path, with its concrete path, until the callback returns
+/// `ControlFlow::Break`.
+///
+/// Contrary to [for_each], the keys of the path are literal: a `*` or
+/// a glob is just a key.
+///
+/// ```
+/// use std::{collections::BTreeMap, ops::ControlFlow};
+///
+/// let stats: BTreeMap<&str, Vec<u32>> = [("cpu*", vec![3, 4]), ("cpu0", vec![5])].into();
+/// let mut children = Vec::new();
+/// iq::for_each_child(&stats, "cpu*", |path, node| {
+///     children.push((path.join("."), node.primitive().unwrap()));
+///     ControlFlow::Continue(())
+/// })
+/// .unwrap();
+/// assert_eq!(children, [("cpu*.0".to_string(), "3".to_string()), ("cpu*.1".to_string(), "4".to_string())]);
+/// ```
+pub fn for_each_child<T, P, F>(
+    source: &T,
+    path: P,
+    f: F,
+) -> Result<(), IqError>
+where
+    T: ?Sized + Serialize,
+    P: IqPath,
+    F: FnMut(&[String], IqNode<'_>) -> ControlFlow<()>,
+{
+    let mut tree = PatternTree::new();
+    tree.add_children_of(&path);
+    walk_with(&tree, source, f)
+}
+
+fn walk_with<T, F>(
+    tree: &PatternTree,
+    source: &T,
+    f: F,
+) -> Result<(), IqError>
+where
+    T: ?Sized + Serialize,
+    F: FnMut(&[String], IqNode<'_>) -> ControlFlow<()>,
+{
+    match Walker::new(tree, Callback { f }).walk(source) {
+        Ok(_) => Ok(()),
+        Err(IqInternalError::Message(msg)) => Err(IqError::Serde(msg)),
+        Err(IqInternalError::Json(err)) => Err(IqError::Json(err)),
+        Err(_) => Ok(()), // not expected
+    }
+}
+
+/// The visitor of `for_each`
+struct Callback<F> {
+    f: F,
+}
+impl<F> Visit for Callback<F>
+where
+    F: FnMut(&[String], IqNode<'_>) -> ControlFlow<()>,
+{
+    fn visit<T>(
+        &mut self,
+        _node: usize,
+        path: &[String],
+        value: &T,
+    ) -> Result<ControlFlow<()>, IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        let node = IqNode {
+            value: &Wrapped(value),
+        };
+        Ok((self.f)(path, node))
+    }
+}
+
+#[test]
+fn test_for_each() {
+    use std::collections::BTreeMap;
+    #[derive(Serialize)]
+    struct Passenger {
+        name: &'static str,
+        bags: Vec<u8>,
+    }
+    let passengers: BTreeMap<&str, Passenger> = [
+        (
+            "p1",
+            Passenger {
+                name: "Ann",
+                bags: vec![3, 4],
+            },
+        ),
+        (
+            "p2",
+            Passenger {
+                name: "Bob",
+                bags: vec![],
+            },
+        ),
+        (
+            "p3",
+            Passenger {
+                name: "Cid",
+                bags: vec![7],
+            },
+        ),
+    ]
+    .into_iter()
+    .collect();
+    let mut seen = Vec::new();
+    for_each(&passengers, "*", |path, node| {
+        seen.push((
+            path.join("."),
+            node.size(),
+            node.primitive(),
+            node.json().unwrap(),
+        ));
+        ControlFlow::Continue(())
+    })
+    .unwrap();
+    assert_eq!(
+        seen,
+        vec![
+            (
+                "p1".to_string(),
+                Some(2),
+                None,
+                r#"{"name":"Ann","bags":[3,4]}"#.to_string(),
+            ),
+            (
+                "p2".to_string(),
+                Some(2),
+                None,
+                r#"{"name":"Bob","bags":[]}"#.to_string(),
+            ),
+            (
+                "p3".to_string(),
+                Some(2),
+                None,
+                r#"{"name":"Cid","bags":[7]}"#.to_string(),
+            ),
+        ],
+    );
+    // stopping early
+    let mut names = Vec::new();
+    for_each(&passengers, "p*.name", |_, node| {
+        names.push(node.primitive().unwrap());
+        if names.len() == 2 {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    })
+    .unwrap();
+    assert_eq!(names, vec!["Ann", "Bob"]);
+    // deserializing
+    let mut bags: Vec<Vec<u32>> = Vec::new();
+    for_each(&passengers, "*.bags", |_, node| {
+        bags.push(node.deserialize().unwrap());
+        ControlFlow::Continue(())
+    })
+    .unwrap();
+    assert_eq!(bags, vec![vec![3, 4], vec![], vec![7]]);
+    // extracting from a node
+    let mut first_bags = Vec::new();
+    for_each(&passengers, "*", |_, node| {
+        first_bags.push(node.extract_primitive("bags.0"));
+        ControlFlow::Continue(())
+    })
+    .unwrap();
+    assert_eq!(
+        first_bags,
+        vec![Some("3".to_string()), None, Some("7".to_string())]
+    );
+    assert!(for_each(&passengers, "nothing", |_, _| unreachable!()).is_ok());
+}
@@ -0,0 +1,322 @@
+//! Formatters for other formats than JSON, each one behind its feature.
+//!
+//! The text formats don't end with a newline, even when they're
+//! multi-line documents. The binary formats render as bytes.
+//!
+//! A format may not be able to represent some values (eg TOML has no null).
+//! When it's the found value itself which can't be represented, the
+//! formatter renders nothing, as when the path isn't found. When it's a
+//! value deeper in the found one, the formatter returns an error, unless
+//! the format has a standard way to omit it (eg TOML omits null fields).
+use {
+    crate::*,
+    serde::Serialize,
+};
+
+/// A formatter rendering the value as YAML
+#[cfg(feature = "yaml")]
+#[cfg_attr(docsrs, doc(cfg(feature = "yaml")))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Yaml;
+
+#[cfg(feature = "yaml")]
+impl IqFormatter for Yaml {
+    type Output = String;
+    fn format(
+        &mut self,
+        value: &dyn erased_serde::Serialize,
+    ) -> Result<Option<String>, IqError> {
+        let yaml = serde_norway::to_string(value).map_err(|e| IqError::Serde(e.to_string()))?;
+        Ok(Some(trim_newline(yaml)))
+    }
+}
+
+/// A formatter rendering the value as TOML.
+///
+/// Tables are rendered as documents, other values (eg a string or
+/// an array) as they'd be written at the right of a `=`.
+///
+/// As TOML has no null, null fields of tables are omitted and a found
+/// null value renders nothing. Fields are kept in their order.
+#[cfg(feature = "toml")]
+#[cfg_attr(docsrs, doc(cfg(feature = "toml")))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Toml;
+
+#[cfg(feature = "toml")]
+impl IqFormatter for Toml {
+    type Output = String;
+    fn format(
+        &mut self,
+        value: &dyn erased_serde::Serialize,
+    ) -> Result<Option<String>, IqError> {
+        let toml_value = match toml::Value::try_from(value) {
+            Ok(toml_value) => toml_value,
+            Err(_) if serde_json::to_value(value).is_ok_and(|v| v.is_null()) => {
+                return Ok(None);
+            }
+            Err(e) => {
+                return Err(IqError::Serde(e.to_string()));
+            }
+        };
+        let toml = match toml_value {
+            toml::Value::Table(table) => {
+                toml::to_string(&table).map_err(|e| IqError::Serde(e.to_string()))?
+            }
+            toml_value => toml_value.to_string(),
+        };
+        Ok(Some(trim_newline(toml)))
+    }
+}
+
+/// A formatter rendering the value as RON
+#[cfg(feature = "ron")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ron")))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Ron;
+
+#[cfg(feature = "ron")]
+impl IqFormatter for Ron {
+    type Output = String;
+    fn format(
+        &mut self,
+        value: &dyn erased_serde::Serialize,
+    ) -> Result<Option<String>, IqError> {
+        let ron = ron::to_string(value).map_err(|e| IqError::Serde(e.to_string()))?;
+        Ok(Some(ron))
+    }
+}
+
+/// A formatter rendering the value as CBOR
+#[cfg(feature = "cbor")]
+#[cfg_attr(docsrs, doc(cfg(feature = "cbor")))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Cbor;
+
+#[cfg(feature = "cbor")]
+impl IqFormatter for Cbor {
+    type Output = Vec<u8>;
+    fn format(
+        &mut self,
+        value: &dyn erased_serde::Serialize,
+    ) -> Result<Option<Vec<u8>>, IqError> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(value, &mut bytes).map_err(|e| IqError::Serde(e.to_string()))?;
+        Ok(Some(bytes))
+    }
+}
+
+/// A formatter rendering the value as MessagePack.
+///
+/// Structs are written as maps, with their field names.
+#[cfg(feature = "msgpack")]
+#[cfg_attr(docsrs, doc(cfg(feature = "msgpack")))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MsgPack;
+
+#[cfg(feature = "msgpack")]
+impl IqFormatter for MsgPack {
+    type Output = Vec<u8>;
+    fn format(
+        &mut self,
+        value: &dyn erased_serde::Serialize,
+    ) -> Result<Option<Vec<u8>>, IqError> {
+        let bytes = rmp_serde::to_vec_named(value).map_err(|e| IqError::Serde(e.to_string()))?;
+        Ok(Some(bytes))
+    }
+}
+
+#[cfg(any(feature = "yaml", feature = "toml"))]
+fn trim_newline(mut s: String) -> String {
+    while s.ends_with('\n') {
+        s.pop();
+    }
+    s
+}
+
+/// Extract a value as YAML
+///
+/// ```
+/// let data = vec![("Rex", 2), ("Laïka", 2)];
+/// assert_eq!(iq::extract_yaml(&data, "1").unwrap(), "- Laïka\n- 2");
+/// ```
+#[cfg(feature = "yaml")]
+#[cfg_attr(docsrs, doc(cfg(feature = "yaml")))]
+pub fn extract_yaml<T: Serialize, P: IqPath>(
+    source: &T,
+    path: P,
+) -> Option<String> {
+    extract_formatted(source, path, Yaml, &IqOptions::default()).unwrap_or(None)
+}
+
+/// Extract a value as TOML (see [Toml] for the handling of non
+/// table values and nulls)
+#[cfg(feature = "toml")]
+#[cfg_attr(docsrs, doc(cfg(feature = "toml")))]
+pub fn extract_toml<T: Serialize, P: IqPath>(
+    source: &T,
+    path: P,
+) -> Option<String> {
+    extract_formatted(source, path, Toml, &IqOptions::default()).unwrap_or(None)
+}
+
+/// Extract a value as RON
+#[cfg(feature = "ron")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ron")))]
+pub fn extract_ron<T: Serialize, P: IqPath>(
+    source: &T,
+    path: P,
+) -> Option<String> {
+    extract_formatted(source, path, Ron, &IqOptions::default()).unwrap_or(None)
+}
+
+/// Extract a value as CBOR
+#[cfg(feature = "cbor")]
+#[cfg_attr(docsrs, doc(cfg(feature = "cbor")))]
+pub fn extract_cbor<T: Serialize, P: IqPath>(
+    source: &T,
+    path: P,
+) -> Option<Vec<u8>> {
+    extract_formatted(source, path, Cbor, &IqOptions::default()).unwrap_or(None)
+}
+
+/// Extract a value as MessagePack
+///
+/// ```
+/// let data = (("a", 'b'), [1, 2, 3]);
+/// assert_eq!(iq::extract_msgpack(&data, "1").unwrap(), vec![0x93, 1, 2, 3]);
+/// ```
+#[cfg(feature = "msgpack")]
+#[cfg_attr(docsrs, doc(cfg(feature = "msgpack")))]
+pub fn extract_msgpack<T: Serialize, P: IqPath>(
+    source: &T,
+    path: P,
+) -> Option<Vec<u8>> {
+    extract_formatted(source, path, MsgPack, &IqOptions::default()).unwrap_or(None)
+}
+
+#[cfg(any(feature = "yaml", feature = "toml", feature = "ron"))]
+#[test]
+fn test_text_formats() {
+    use std::collections::BTreeMap;
+    #[derive(Serialize)]
+    struct Server {
+        host: &'static str,
+        port: u16,
+        proxy: Option<&'static str>,
+        tags: Vec<&'static str>,
+    }
+    let servers: BTreeMap<&str, Server> = [
+        (
+            "main",
+            Server {
+                host: "example.com",
+                port: 443,
+                proxy: None,
+                tags: vec!["prod"],
+            },
+        ),
+        (
+            "test",
+            Server {
+                host: "localhost",
+                port: 8080,
+                proxy: Some("squid"),
+                tags: vec![],
+            },
+        ),
+    ]
+    .into_iter()
+    .collect();
+    #[cfg(feature = "yaml")]
+    {
+        assert_eq!(
+            extract_yaml(&servers, "main").unwrap(),
+            "host: example.com\nport: 443\nproxy: null\ntags:\n- prod",
+        );
+        assert_eq!(extract_yaml(&servers, "test.port").unwrap(), "8080");
+        assert_eq!(extract_yaml(&servers, "main.proxy").unwrap(), "null");
+        assert_eq!(extract_yaml(&servers, "other"), None);
+    }
+    #[cfg(feature = "toml")]
+    {
+        assert_eq!(
+            extract_toml(&servers, "main").unwrap(),
+            "host = \"example.com\"\nport = 443\ntags = [\"prod\"]",
+        );
+        assert_eq!(
+            extract_toml(&servers, "test.host").unwrap(),
+            "\"localhost\""
+        );
+        assert_eq!(extract_toml(&servers, "main.tags").unwrap(), "[\"prod\"]");
+        assert_eq!(extract_toml(&servers, "main.proxy"), None);
+        assert_eq!(extract_toml(&servers, "test.proxy").unwrap(), "\"squid\"");
+        assert!(
+            extract_toml(&servers, Vec::<&str>::new())
+                .unwrap()
+                .starts_with("[main]\nhost = \"example.com\"")
+        );
+        // a null which isn't a field can't be omitted
+        let holes = vec![Some(1), None];
+        assert!(
+            extract_formatted(&holes, Vec::<&str>::new(), Toml, &IqOptions::default()).is_err()
+        );
+        // fields are kept in their order
+        #[derive(Serialize)]
+        struct Release {
+            version: &'static str,
+            name: &'static str,
+            date: &'static str,
+        }
+        let release = Release {
+            version: "1.2",
+            name: "Zebra",
+            date: "2024-05-27",
+        };
+        assert_eq!(
+            extract_toml(&[release], "0").unwrap(),
+            "version = \"1.2\"\nname = \"Zebra\"\ndate = \"2024-05-27\"",
+        );
+    }
+    #[cfg(feature = "ron")]
+    {
+        assert_eq!(
+            extract_ron(&servers, "test").unwrap(),
+            r#"(host:"localhost",port:8080,proxy:Some("squid"),tags:[])"#,
+        );
+        assert_eq!(extract_ron(&servers, "main.proxy").unwrap(), "None");
+    }
+}
+
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+#[test]
+fn test_binary_formats() {
+    use std::collections::HashMap;
+    #[derive(Serialize)]
+    struct Fragment {
+        id: u32,
+        parts: HashMap<&'static str, Vec<i64>>,
+    }
+    let fragments = vec![Fragment {
+        id: 7,
+        parts: [("head", vec![-1, 300])].into_iter().collect(),
+    }];
+    #[cfg(feature = "cbor")]
+    {
+        let bytes = extract_cbor(&fragments, "0.parts").unwrap();
+        let parts: HashMap<String, Vec<i64>> = ciborium::from_reader(bytes.as_slice()).unwrap();
+        assert_eq!(parts["head"], vec![-1, 300]);
+        assert_eq!(extract_cbor(&fragments, "0.id").unwrap(), vec![0x07]);
+        assert_eq!(extract_cbor(&fragments, "1"), None);
+    }
+    #[cfg(feature = "msgpack")]
+    {
+        let bytes = extract_msgpack(&fragments, "0").unwrap();
+        let fragment: serde_json::Value = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(
+            fragment,
+            serde_json::json!({"id": 7, "parts": {"head": [-1, 300]}})
+        );
+        assert_eq!(extract_msgpack(&fragments, "0.parts.tail"), None);
+    }
+}
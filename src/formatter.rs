@@ -0,0 +1,154 @@
+use {
+    crate::{
+        diver::render_primitive,
+        *,
+    },
+    std::io,
+};
+
+/// A renderer of the value found at the end of a path.
+///
+/// Implement it to extract values in your own format (YAML, a binary
+/// format, a custom text, etc.) with [extract_formatted].
+///
+/// ```
+/// use iq::*;
+///
+/// /// A formatter rendering the value in JSON, with its length
+/// struct Sized;
+/// impl IqFormatter for Sized {
+///     type Output = String;
+///     fn format(
+///         &mut self,
+///         value: &dyn erased_serde::Serialize,
+///     ) -> Result<Option<String>, IqError> {
+///         let json = serde_json::to_string(value)?;
+///         Ok(Some(format!("{} ({} bytes)", json, json.len())))
+///     }
+/// }
+/// let data = vec![("a", 1), ("b", 22)];
+/// assert_eq!(
+///     extract_formatted(&data, "1", Sized, &IqOptions::default()).unwrap(),
+///     Some(r#"["b",22] (8 bytes)"#.to_string()),
+/// );
+/// ```
+pub trait IqFormatter {
+    type Output;
+    /// Render the found value, or return `None` if it can't be
+    /// rendered in this format (eg a struct for a primitive format).
+    fn format(
+        &mut self,
+        value: &dyn erased_serde::Serialize,
+    ) -> Result<Option<Self::Output>, IqError>;
+}
+
+impl<F: IqFormatter + ?Sized> IqFormatter for &mut F {
+    type Output = F::Output;
+    fn format(
+        &mut self,
+        value: &dyn erased_serde::Serialize,
+    ) -> Result<Option<Self::Output>, IqError> {
+        (**self).format(value)
+    }
+}
+
+/// The built-in formats, rendering as strings
+impl IqFormatter for IqFormat {
+    type Output = String;
+    fn format(
+        &mut self,
+        value: &dyn erased_serde::Serialize,
+    ) -> Result<Option<String>, IqError> {
+        match self {
+            Self::Primitive => render_primitive(value),
+            Self::Json => Ok(Some(serde_json::to_string(value)?)),
+            Self::JsonPretty => Ok(Some(serde_json::to_string_pretty(value)?)),
+            Self::Size => Ok(Sizer::count(value).map(|count| count.to_string())),
+        }
+    }
+}
+
+/// A formatter writing the value as JSON into a writer
+pub(crate) struct JsonWriter<W> {
+    pub writer: W,
+    pub pretty: bool,
+}
+impl<W: io::Write> IqFormatter for JsonWriter<W> {
+    type Output = ();
+    fn format(
+        &mut self,
+        value: &dyn erased_serde::Serialize,
+    ) -> Result<Option<()>, IqError> {
+        if self.pretty {
+            serde_json::to_writer_pretty(&mut self.writer, value)?;
+        } else {
+            serde_json::to_writer(&mut self.writer, value)?;
+        }
+        Ok(Some(()))
+    }
+}
+
+/// A formatter counting the items of the array/map/struct/tupple/string
+pub(crate) struct SizeCounter;
+impl IqFormatter for SizeCounter {
+    type Output = usize;
+    fn format(
+        &mut self,
+        value: &dyn erased_serde::Serialize,
+    ) -> Result<Option<usize>, IqError> {
+        Ok(Sizer::count(value))
+    }
+}
+
+#[test]
+fn test_formatters() {
+    use std::collections::BTreeMap;
+    let data: BTreeMap<&str, (u8, Vec<&str>)> = [("a", (1, vec!["x", "y"])), ("b", (2, vec![]))]
+        .into_iter()
+        .collect();
+    let options = IqOptions::default();
+    assert_eq!(
+        extract_formatted(&data, Vec::<&str>::new(), IqFormat::Json, &options).unwrap(),
+        Some(r#"{"a":[1,["x","y"]],"b":[2,[]]}"#.to_string()),
+    );
+    assert_eq!(
+        extract_formatted(&data, "a.1", IqFormat::Size, &options).unwrap(),
+        Some("2".to_string()),
+    );
+    assert_eq!(
+        extract_formatted(&data, "a.1.1", IqFormat::Primitive, &options).unwrap(),
+        Some("y".to_string()),
+    );
+    assert_eq!(
+        extract_formatted(&data, "a.1", IqFormat::Primitive, &options).unwrap(),
+        None,
+    );
+    // a formatter refusing some values, and used by reference
+    struct OnlyNumbers {
+        calls: usize,
+    }
+    impl IqFormatter for OnlyNumbers {
+        type Output = f64;
+        fn format(
+            &mut self,
+            value: &dyn erased_serde::Serialize,
+        ) -> Result<Option<f64>, IqError> {
+            self.calls += 1;
+            Ok(serde_json::to_value(value)?.as_f64())
+        }
+    }
+    let mut only_numbers = OnlyNumbers { calls: 0 };
+    assert_eq!(
+        extract_formatted(&data, "b.0", &mut only_numbers, &options).unwrap(),
+        Some(2.0),
+    );
+    assert_eq!(
+        extract_formatted(&data, "b.1", &mut only_numbers, &options).unwrap(),
+        None,
+    );
+    assert_eq!(
+        extract_formatted(&data, "c", &mut only_numbers, &options).unwrap(),
+        None,
+    );
+    assert_eq!(only_numbers.calls, 2);
+}
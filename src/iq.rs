@@ -15,6 +15,26 @@ pub trait IQ {
         path: P,
     ) -> Option<String>;
 
+    /// Extract "primitive" values at several paths, in one visit
+    fn extract_many<P: IqPath>(
+        &self,
+        paths: &[P],
+    ) -> Vec<Option<String>>;
+
+    /// Extract "primitive" values, with their concrete paths, at all the
+    /// paths matching a pattern (eg `metrics.cpu_*`)
+    fn extract_all<P: IqPath>(
+        &self,
+        pattern: P,
+    ) -> Vec<(Vec<String>, String)>;
+
+    /// Iterate over the values matching a pattern (eg `passengers.*`),
+    /// with their concrete paths
+    fn iq_iter<P: IqPath>(
+        &self,
+        pattern: P,
+    ) -> IqIter<'_, Self>;
+
     /// Extract a value as JSON
     fn extract_json<P: IqPath>(
         &self,
@@ -27,6 +47,46 @@ pub trait IQ {
         path: P,
     ) -> Option<String>;
 
+    /// Extract a value as YAML
+    #[cfg(feature = "yaml")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "yaml")))]
+    fn extract_yaml<P: IqPath>(
+        &self,
+        path: P,
+    ) -> Option<String>;
+
+    /// Extract a value as TOML
+    #[cfg(feature = "toml")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "toml")))]
+    fn extract_toml<P: IqPath>(
+        &self,
+        path: P,
+    ) -> Option<String>;
+
+    /// Extract a value as RON
+    #[cfg(feature = "ron")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "ron")))]
+    fn extract_ron<P: IqPath>(
+        &self,
+        path: P,
+    ) -> Option<String>;
+
+    /// Extract a value as CBOR
+    #[cfg(feature = "cbor")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "cbor")))]
+    fn extract_cbor<P: IqPath>(
+        &self,
+        path: P,
+    ) -> Option<Vec<u8>>;
+
+    /// Extract a value as MessagePack
+    #[cfg(feature = "msgpack")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "msgpack")))]
+    fn extract_msgpack<P: IqPath>(
+        &self,
+        path: P,
+    ) -> Option<Vec<u8>>;
+
     /// Extract a value in a type which must implement `Deserialize`, from a value, at
     /// the given path.
     ///
@@ -56,6 +116,27 @@ where
         extract_primitive(self, path)
     }
 
+    fn extract_many<P: IqPath>(
+        &self,
+        paths: &[P],
+    ) -> Vec<Option<String>> {
+        extract_many(self, paths)
+    }
+
+    fn extract_all<P: IqPath>(
+        &self,
+        pattern: P,
+    ) -> Vec<(Vec<String>, String)> {
+        extract_all(self, pattern)
+    }
+
+    fn iq_iter<P: IqPath>(
+        &self,
+        pattern: P,
+    ) -> IqIter<'_, Self> {
+        IqIter::new(self, pattern)
+    }
+
     fn extract_json<P: IqPath>(
         &self,
         path: P,
@@ -70,6 +151,46 @@ where
         extract_json(self, path)
     }
 
+    #[cfg(feature = "yaml")]
+    fn extract_yaml<P: IqPath>(
+        &self,
+        path: P,
+    ) -> Option<String> {
+        extract_yaml(self, path)
+    }
+
+    #[cfg(feature = "toml")]
+    fn extract_toml<P: IqPath>(
+        &self,
+        path: P,
+    ) -> Option<String> {
+        extract_toml(self, path)
+    }
+
+    #[cfg(feature = "ron")]
+    fn extract_ron<P: IqPath>(
+        &self,
+        path: P,
+    ) -> Option<String> {
+        extract_ron(self, path)
+    }
+
+    #[cfg(feature = "cbor")]
+    fn extract_cbor<P: IqPath>(
+        &self,
+        path: P,
+    ) -> Option<Vec<u8>> {
+        extract_cbor(self, path)
+    }
+
+    #[cfg(feature = "msgpack")]
+    fn extract_msgpack<P: IqPath>(
+        &self,
+        path: P,
+    ) -> Option<Vec<u8>> {
+        extract_msgpack(self, path)
+    }
+
     fn extract_value<P: IqPath, V: DeserializeOwned>(
         &self,
         path: P,
@@ -0,0 +1,242 @@
+use {
+    crate::{
+        errors::IqInternalError,
+        pattern::PatternTree,
+        walker::*,
+        *,
+    },
+    serde::Serialize,
+    std::{
+        fmt,
+        ops::ControlFlow,
+        vec,
+    },
+};
+
+/// The keys of the path leading to a value, as found during a visit
+pub type ConcretePath = Vec<String>;
+
+/// A value rendered as a string if it's a "primitive", or as JSON
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum JsonOrScalar {
+    /// A primitive value, rendered as by `extract_primitive`
+    Scalar(String),
+    /// A non primitive value, as JSON
+    Json(String),
+}
+
+impl JsonOrScalar {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Scalar(s) | Self::Json(s) => s,
+        }
+    }
+    pub fn is_scalar(&self) -> bool {
+        matches!(self, Self::Scalar(_))
+    }
+}
+
+impl fmt::Display for JsonOrScalar {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The number of matches found by the first walk of an `IqIter`
+const FIRST_CHUNK: usize = 16;
+
+/// An iterator over the values matching a pattern, with their
+/// concrete paths.
+///
+/// As serde serialization can't be paused, the value is walked by chunks
+/// of matches, each walk skipping the matches of the previous ones. The
+/// size of the chunks doubles at each walk, so that a short iteration is
+/// cheap while a complete one stays linear.
+///
+/// As every walk starts from the root, the source must serialize the
+/// same way on every walk (eg a value with interior mutability must not
+/// be modified during the iteration).
+///
+/// The iteration stops at the first serialization error, after the
+/// matches found before it. The error is then given by
+/// [`IqIter::last_error`].
+///
+/// ```
+/// use iq::*;
+///
+/// let dogs = vec![("Rex", 2), ("Laïka", 2), ("Roverandom", 1)];
+/// let names: Vec<String> = dogs
+///     .iq_iter("*.0")
+///     .map(|(_, name)| name.to_string())
+///     .filter(|name| name.starts_with('R'))
+///     .collect();
+/// assert_eq!(names, vec!["Rex", "Roverandom"]);
+/// let (path, ears) = dogs.iq_iter("*.1").last().unwrap();
+/// assert_eq!(path, vec!["2", "1"]);
+/// assert_eq!(ears, JsonOrScalar::Scalar("1".to_string()));
+/// ```
+pub struct IqIter<'v, T: ?Sized> {
+    source: &'v T,
+    tree: PatternTree,
+    buffer: vec::IntoIter<(ConcretePath, JsonOrScalar)>,
+    /// the number of matches found by the previous walks
+    walked: usize,
+    /// the number of matches to find in the next walk
+    chunk: usize,
+    done: bool,
+    error: Option<IqError>,
+}
+
+impl<'v, T: ?Sized + Serialize> IqIter<'v, T> {
+    pub fn new<P: IqPath>(
+        source: &'v T,
+        pattern: P,
+    ) -> Self {
+        let mut tree = PatternTree::new();
+        tree.add(&pattern);
+        Self {
+            source,
+            tree,
+            buffer: Vec::new().into_iter(),
+            walked: 0,
+            chunk: FIRST_CHUNK,
+            done: false,
+            error: None,
+        }
+    }
+    /// The serialization error which stopped the iteration, if any
+    pub fn last_error(&self) -> Option<&IqError> {
+        self.error.as_ref()
+    }
+    fn walk_next_chunk(&mut self) {
+        let mut chunker = Chunker {
+            skip: self.walked,
+            seen: 0,
+            limit: self.chunk,
+            found: Vec::with_capacity(self.chunk),
+        };
+        match Walker::new(&self.tree, &mut chunker).walk(self.source) {
+            Ok(_) => {}
+            Err(IqInternalError::Message(msg)) => self.error = Some(IqError::Serde(msg)),
+            Err(IqInternalError::Json(err)) => self.error = Some(IqError::Json(err)),
+            Err(_) => {} // not expected
+        }
+        let found = chunker.found;
+        if found.len() < self.chunk || self.error.is_some() {
+            self.done = true;
+        }
+        self.walked += found.len();
+        self.chunk *= 2;
+        self.buffer = found.into_iter();
+    }
+}
+
+impl<T: ?Sized + Serialize> Iterator for IqIter<'_, T> {
+    type Item = (ConcretePath, JsonOrScalar);
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(item) = self.buffer.next() {
+            return Some(item);
+        }
+        if self.done {
+            return None;
+        }
+        self.walk_next_chunk();
+        self.buffer.next()
+    }
+}
+
+/// The visitor of an `IqIter` walk, rendering a chunk of matches
+struct Chunker {
+    /// the number of matches to skip, as they were found by previous walks
+    skip: usize,
+    seen: usize,
+    limit: usize,
+    found: Vec<(ConcretePath, JsonOrScalar)>,
+}
+impl Visit for Chunker {
+    fn visit<T>(
+        &mut self,
+        _node: usize,
+        path: &[String],
+        value: &T,
+    ) -> Result<ControlFlow<()>, IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.seen += 1;
+        if self.seen <= self.skip {
+            return Ok(ControlFlow::Continue(()));
+        }
+        let rendered =
+            match extract_string_checked(&value, Vec::<&str>::new(), IqFormat::Primitive)? {
+                Some(scalar) => JsonOrScalar::Scalar(scalar),
+                None => JsonOrScalar::Json(serde_json::to_string(value)?),
+            };
+        self.found.push((path.to_vec(), rendered));
+        if self.found.len() == self.limit {
+            Ok(ControlFlow::Break(()))
+        } else {
+            Ok(ControlFlow::Continue(()))
+        }
+    }
+}
+
+#[test]
+fn test_iq_iter() {
+    #[derive(Serialize)]
+    struct Passenger {
+        name: String,
+        seat: Option<u32>,
+    }
+    let passengers: Vec<Passenger> = (0..100)
+        .map(|i| Passenger {
+            name: format!("p{i}"),
+            seat: (i % 3 != 0).then_some(i),
+        })
+        .collect();
+    // all matches are found, in order, whatever the chunk boundaries
+    let all: Vec<_> = passengers.iq_iter("*.name").collect();
+    assert_eq!(all.len(), 100);
+    for (i, (path, name)) in all.iter().enumerate() {
+        assert_eq!(path, &vec![i.to_string(), "name".to_string()]);
+        assert_eq!(name, &JsonOrScalar::Scalar(format!("p{i}")));
+    }
+    // composing with iterator adapters
+    let seats: Vec<String> = passengers
+        .iq_iter("*.seat")
+        .filter(|(_, seat)| seat.as_str() != "none")
+        .skip(20)
+        .take(3)
+        .map(|(_, seat)| seat.to_string())
+        .collect();
+    assert_eq!(seats, vec!["31", "32", "34"]);
+    assert_eq!(
+        passengers.iq_iter("7").next().unwrap().1,
+        JsonOrScalar::Json(r#"{"name":"p7","seat":7}"#.to_string()),
+    );
+    assert_eq!(passengers.iq_iter("*.age").count(), 0);
+    assert_eq!(IqIter::new(&passengers, "*").count(), 100);
+    assert!(IqIter::new(&passengers, "*").last_error().is_none());
+
+    // the matches found before a serialization error are kept
+    struct Item(usize);
+    impl Serialize for Item {
+        fn serialize<S: serde::Serializer>(
+            &self,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            if self.0 == 20 {
+                return Err(serde::ser::Error::custom("boom"));
+            }
+            serializer.serialize_u64(self.0 as u64)
+        }
+    }
+    let items: Vec<Item> = (0..30).map(Item).collect();
+    let mut iter = IqIter::new(&items, "*");
+    let found: Vec<_> = iter.by_ref().map(|(_, item)| item.to_string()).collect();
+    assert_eq!(found, (0..20).map(|i| i.to_string()).collect::<Vec<_>>());
+    assert!(matches!(iter.last_error(), Some(IqError::Serde(msg)) if msg.contains("boom")));
+}
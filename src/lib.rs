@@ -89,8 +89,17 @@
 //! assert_eq!(iq::size_of(&("a", 1)), Some(2)); // works with tuples too
 //!
 //! // If iq is compiled with the "template" feature, you get a mini templating utility
-//! let template = iq::Template::new("{driver.name} drives a {engine} car.");
-//! assert_eq!(template.render(&car), "Rex drives a V8 car.");
+//! #[cfg(feature = "template")]
+//! {
+//!     let template = iq::Template::new("{driver.name} drives a {engine} car.");
+//!     assert_eq!(template.render(&car), "Rex drives a V8 car.");
+//!     let template = iq::Template::new(r#"{driver.licence ?? "no licence"}"#); // with a default value
+//!     assert_eq!(template.render(&car), "no licence");
+//!     let template = iq::Template::new("{driver.name | upper}"); // with a filter
+//!     assert_eq!(template.render(&car), "REX");
+//!     let template = iq::Template::new("{#each passengers}{name} {/each}"); // with a loop
+//!     assert_eq!(template.render(&car), "Roverandom Laïka ");
+//! }
 //!
 //! ```
 //!
@@ -99,24 +108,65 @@
 
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+mod diff;
 mod diver;
 mod errors;
 mod extract;
+mod for_each;
+mod formatter;
 mod iq;
+mod iter;
+mod map_key;
+mod merge;
+mod options;
 mod path;
+mod pattern;
+mod project;
+mod query;
+mod query_set;
+mod redact;
+mod rewrite;
 mod sizer;
+mod walker;
 
+#[cfg(any(
+    feature = "yaml",
+    feature = "toml",
+    feature = "ron",
+    feature = "cbor",
+    feature = "msgpack",
+))]
+mod formats;
 #[cfg(feature = "template")]
 mod template;
 
+pub use erased_serde; // so that formatters can be written without depending on it
 pub(crate) use sizer::Sizer;
 pub use {
+    diff::*,
     errors::IqError,
     extract::*,
+    for_each::*,
+    formatter::IqFormatter,
     iq::*,
+    iter::*,
+    merge::*,
+    options::*,
     path::*,
+    project::*,
+    query::*,
+    query_set::QuerySet,
+    redact::*,
 };
 
+#[cfg(any(
+    feature = "yaml",
+    feature = "toml",
+    feature = "ron",
+    feature = "cbor",
+    feature = "msgpack",
+))]
+pub use formats::*;
 #[cfg(feature = "template")]
 #[cfg_attr(docsrs, doc(cfg(feature = "template")))]
 pub use template::*;
@@ -250,4 +300,75 @@ mod tests {
             }),
         );
     }
+
+    #[test]
+    fn typed_map_keys() {
+        use std::collections::BTreeMap;
+        #[derive(Debug, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+        struct Id(u16);
+        let ints: BTreeMap<i32, &str> = [(-1, "minus one"), (12, "twelve")].into_iter().collect();
+        assert_eq!(ints.extract_primitive("-1").unwrap(), "minus one");
+        assert_eq!(ints.extract_primitive("12").unwrap(), "twelve");
+        let ids: BTreeMap<Id, bool> = [(Id(3), true)].into_iter().collect();
+        assert_eq!(ids.extract_primitive("3").unwrap(), "true");
+        let flags: BTreeMap<bool, char> = [(false, 'n'), (true, 'y')].into_iter().collect();
+        assert_eq!(flags.extract_primitive("true").unwrap(), "y");
+        let chars: BTreeMap<char, u8> = [('é', 1)].into_iter().collect();
+        assert_eq!(chars.extract_primitive("é").unwrap(), "1");
+        // string keys are compared exactly, without escaping
+        let quoted: HashMap<&str, u8> = [(r#"a"b"#, 1), (r"a\b", 2)].into_iter().collect();
+        assert_eq!(quoted.extract_primitive(r#"a"b"#).unwrap(), "1");
+        assert_eq!(quoted.extract_primitive(r"a\b").unwrap(), "2");
+        // integer keys are compared numerically
+        assert_eq!(ints.extract_primitive("012").unwrap(), "twelve");
+        assert_eq!(ints.extract_primitive("-01").unwrap(), "minus one");
+        let strings: BTreeMap<&str, u8> = [("01", 1)].into_iter().collect();
+        assert_eq!(strings.extract_primitive("01").unwrap(), "1");
+        assert_eq!(strings.extract_primitive("1"), None);
+    }
+
+    #[test]
+    fn composite_map_keys() {
+        use std::collections::BTreeMap;
+        #[derive(Debug, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+        struct Pos {
+            x: i32,
+            y: i32,
+        }
+        #[derive(Debug, Serialize)]
+        struct Cell {
+            value: &'static str,
+        }
+        let grid: HashMap<(u32, u32), Cell> =
+            [((3, 4), Cell { value: "a" }), ((4, 3), Cell { value: "b" })]
+                .into_iter()
+                .collect();
+        assert_eq!(grid.extract_primitive("(3,4).value").unwrap(), "a");
+        assert_eq!(grid.extract_primitive("( 4, 03 ).value").unwrap(), "b");
+        assert_eq!(grid.extract_primitive("[[3,4]].value").unwrap(), "a");
+        assert_eq!(grid.extract_primitive("(3,5).value"), None);
+        let positions: BTreeMap<Pos, &str> = [(Pos { x: 3, y: 4 }, "a")].into_iter().collect();
+        assert_eq!(
+            positions.extract_primitive(r#"[{"x":3,"y":4}]"#).unwrap(),
+            "a"
+        );
+        assert_eq!(
+            positions.extract_primitive(r#"[{"y":4,"x":3}]"#).unwrap(),
+            "a"
+        );
+        assert_eq!(positions.extract_primitive(r#"[{"x":3}]"#), None);
+        let named: BTreeMap<(&str, (u8, bool)), u8> =
+            [(("a.b", (1, true)), 5)].into_iter().collect();
+        assert_eq!(named.extract_primitive("(a.b,(1,true))").unwrap(), "5");
+        assert_eq!(named.extract_primitive(r#"("a.b",(1,true))"#).unwrap(), "5");
+        // the same syntax is used in patterns
+        assert_eq!(
+            super::extract_many(&grid, &["(4,3).value", "(4,3).nothing"]),
+            vec![Some("b".to_string()), None],
+        );
+        assert_eq!(
+            super::extract_many(&positions, &[r#"{"x":3,"y":4}"#]),
+            vec![Some("a".to_string())],
+        );
+    }
 }
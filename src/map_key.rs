@@ -0,0 +1,452 @@
+use {
+    crate::{
+        errors::IqInternalError,
+        options::KeyMatching,
+        path::Nesting,
+    },
+    serde::{
+        Serialize,
+        ser,
+    },
+    serde_json::Value,
+    std::fmt::Write,
+};
+
+/// A map key, rendered for comparison with the keys of a path.
+///
+/// The rendering is exact, without quotes nor escaping, and the buffer is
+/// reused from key to key so that rendering doesn't allocate once the
+/// buffer is big enough:
+/// - a string or a char is taken as is (the `a"b` key is matched by `a"b`)
+/// - a bool or a float is written as by `extract_primitive` (`true`, `1.5`)
+/// - an integer is compared numerically (the `1` key is matched by `01`)
+/// - a unit variant is rendered as its name
+/// - an option, a newtype struct or a newtype variant is rendered as its
+///   content
+/// - a composite key (tuple, struct, sequence, map) is compared structurally
+///   with a path key like `(3,4)` or `{"x":3,"y":4}` (see `composite_key`)
+///
+/// Other keys (unit, none) can't be rendered: they're only matched by
+/// a `*` pattern.
+#[derive(Debug, Default)]
+pub(crate) struct MapKey {
+    buf: String,
+    kind: KeyKind,
+}
+
+#[derive(Debug, Default)]
+enum KeyKind {
+    #[default]
+    Unrendered,
+    Str,
+    Int(i128),
+    Composite(Value),
+}
+
+/// A key of a path, parsed for comparison with map keys
+#[derive(Debug, Clone)]
+pub(crate) struct PathKey {
+    name: String,
+    int: Option<i128>,
+    composite: Option<Value>,
+}
+
+impl PathKey {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            int: name.parse().ok(),
+            composite: composite_key(name),
+        }
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// Return the key as an index in a sequence, if it's one
+    pub fn index(&self) -> Option<usize> {
+        self.int.and_then(|int| usize::try_from(int).ok())
+    }
+}
+
+impl PartialEq for PathKey {
+    fn eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        self.name == other.name
+    }
+}
+impl Eq for PathKey {}
+
+impl MapKey {
+    /// Render a key, replacing the previous one, and return whether
+    /// it could be rendered
+    pub fn render<K>(
+        &mut self,
+        key: &K,
+    ) -> Result<bool, IqInternalError>
+    where
+        K: ?Sized + Serialize,
+    {
+        self.buf.clear();
+        self.kind = match key.serialize(KeyWriter { buf: &mut self.buf }) {
+            Ok(kind) => kind,
+            Err(IqInternalError::NotAKey) => {
+                self.buf.clear();
+                match serde_json::to_value(key)? {
+                    value @ (Value::Array(_) | Value::Object(_)) => {
+                        write!(self.buf, "{}", value)
+                            .map_err(<IqInternalError as ser::Error>::custom)?;
+                        KeyKind::Composite(value)
+                    }
+                    _ => KeyKind::Unrendered,
+                }
+            }
+            Err(err) => return Err(err),
+        };
+        Ok(self.is_rendered())
+    }
+    pub fn is_rendered(&self) -> bool {
+        !matches!(self.kind, KeyKind::Unrendered)
+    }
+    /// Return the last rendered key (as compact JSON for a composite key),
+    /// which is empty if it couldn't be rendered (an empty string doesn't
+    /// match any key of a pattern)
+    pub fn as_str(&self) -> &str {
+        &self.buf
+    }
+    /// Tell whether the last rendered key is designated by the given
+    /// parsed path key
+    pub fn matches(
+        &self,
+        path_key: &PathKey,
+        key_matching: KeyMatching,
+    ) -> bool {
+        match &self.kind {
+            KeyKind::Unrendered => false,
+            KeyKind::Str => key_matching.matches(&path_key.name, &self.buf),
+            KeyKind::Int(int) => path_key.int == Some(*int),
+            KeyKind::Composite(value) => path_key.composite.as_ref() == Some(value),
+        }
+    }
+}
+
+/// Parse a path key designating a composite map key, which is either
+/// - a tuple, like `(3,4)` or `("a",(1,2))`, whose elements are JSON values,
+///   integers or bare strings
+/// - a JSON object or array, like `{"x":3,"y":4}` (which matches a struct
+///   with the same fields, in any order)
+pub(crate) fn composite_key(key: &str) -> Option<Value> {
+    let key = key.trim();
+    if let Some(content) = key.strip_prefix('(').and_then(|k| k.strip_suffix(')')) {
+        let mut elements = Vec::new();
+        if !content.trim().is_empty() {
+            let mut nesting = Nesting::default();
+            let mut start = 0;
+            for (i, c) in content.char_indices() {
+                if c == ',' && nesting.is_top_level() {
+                    elements.push(key_element(&content[start..i]));
+                    start = i + 1;
+                }
+                nesting.push(c);
+            }
+            elements.push(key_element(&content[start..]));
+        }
+        return Some(Value::Array(elements));
+    }
+    if key.starts_with('{') || key.starts_with('[') {
+        return serde_json::from_str(key).ok();
+    }
+    None
+}
+
+fn key_element(element: &str) -> Value {
+    let element = element.trim();
+    composite_key(element)
+        .or_else(|| serde_json::from_str(element).ok())
+        .or_else(|| element.parse::<i64>().ok().map(Value::from))
+        .unwrap_or_else(|| Value::String(element.to_string()))
+}
+
+/// A serializer writing a map key to a buffer
+struct KeyWriter<'b> {
+    buf: &'b mut String,
+}
+impl KeyWriter<'_> {
+    fn write<D: std::fmt::Display>(
+        self,
+        v: D,
+    ) -> Result<KeyKind, IqInternalError> {
+        write!(self.buf, "{}", v).map_err(<IqInternalError as ser::Error>::custom)?;
+        Ok(KeyKind::Str)
+    }
+    fn write_int<I: std::fmt::Display + Copy + TryInto<i128>>(
+        self,
+        v: I,
+    ) -> Result<KeyKind, IqInternalError> {
+        let int = v.try_into().ok();
+        let kind = self.write(v)?;
+        Ok(match int {
+            Some(int) => KeyKind::Int(int),
+            None => kind,
+        })
+    }
+}
+type NotAKey = ser::Impossible<KeyKind, IqInternalError>;
+impl ser::Serializer for KeyWriter<'_> {
+    type Ok = KeyKind;
+    type Error = IqInternalError;
+    type SerializeSeq = NotAKey;
+    type SerializeTuple = NotAKey;
+    type SerializeTupleStruct = NotAKey;
+    type SerializeTupleVariant = NotAKey;
+    type SerializeMap = NotAKey;
+    type SerializeStruct = NotAKey;
+    type SerializeStructVariant = NotAKey;
+    fn serialize_bool(
+        self,
+        v: bool,
+    ) -> Result<KeyKind, IqInternalError> {
+        self.write(v)
+    }
+    fn serialize_i8(
+        self,
+        v: i8,
+    ) -> Result<KeyKind, IqInternalError> {
+        self.write_int(v)
+    }
+    fn serialize_i16(
+        self,
+        v: i16,
+    ) -> Result<KeyKind, IqInternalError> {
+        self.write_int(v)
+    }
+    fn serialize_i32(
+        self,
+        v: i32,
+    ) -> Result<KeyKind, IqInternalError> {
+        self.write_int(v)
+    }
+    fn serialize_i64(
+        self,
+        v: i64,
+    ) -> Result<KeyKind, IqInternalError> {
+        self.write_int(v)
+    }
+    fn serialize_i128(
+        self,
+        v: i128,
+    ) -> Result<KeyKind, IqInternalError> {
+        self.write_int(v)
+    }
+    fn serialize_u8(
+        self,
+        v: u8,
+    ) -> Result<KeyKind, IqInternalError> {
+        self.write_int(v)
+    }
+    fn serialize_u16(
+        self,
+        v: u16,
+    ) -> Result<KeyKind, IqInternalError> {
+        self.write_int(v)
+    }
+    fn serialize_u32(
+        self,
+        v: u32,
+    ) -> Result<KeyKind, IqInternalError> {
+        self.write_int(v)
+    }
+    fn serialize_u64(
+        self,
+        v: u64,
+    ) -> Result<KeyKind, IqInternalError> {
+        self.write_int(v)
+    }
+    fn serialize_u128(
+        self,
+        v: u128,
+    ) -> Result<KeyKind, IqInternalError> {
+        self.write_int(v)
+    }
+    fn serialize_f32(
+        self,
+        v: f32,
+    ) -> Result<KeyKind, IqInternalError> {
+        self.write(v)
+    }
+    fn serialize_f64(
+        self,
+        v: f64,
+    ) -> Result<KeyKind, IqInternalError> {
+        self.write(v)
+    }
+    fn serialize_char(
+        self,
+        v: char,
+    ) -> Result<KeyKind, IqInternalError> {
+        self.buf.push(v);
+        Ok(KeyKind::Str)
+    }
+    fn serialize_str(
+        self,
+        v: &str,
+    ) -> Result<KeyKind, IqInternalError> {
+        self.buf.push_str(v);
+        Ok(KeyKind::Str)
+    }
+    fn serialize_bytes(
+        self,
+        _v: &[u8],
+    ) -> Result<KeyKind, IqInternalError> {
+        Err(IqInternalError::NotAKey)
+    }
+    fn serialize_none(self) -> Result<KeyKind, IqInternalError> {
+        Err(IqInternalError::NotAKey)
+    }
+    fn serialize_some<T>(
+        self,
+        value: &T,
+    ) -> Result<KeyKind, IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<KeyKind, IqInternalError> {
+        Err(IqInternalError::NotAKey)
+    }
+    fn serialize_unit_struct(
+        self,
+        _name: &'static str,
+    ) -> Result<KeyKind, IqInternalError> {
+        Err(IqInternalError::NotAKey)
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<KeyKind, IqInternalError> {
+        self.buf.push_str(variant);
+        Ok(KeyKind::Str)
+    }
+    fn serialize_newtype_struct<T>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<KeyKind, IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<KeyKind, IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+    fn serialize_seq(
+        self,
+        _len: Option<usize>,
+    ) -> Result<NotAKey, IqInternalError> {
+        Err(IqInternalError::NotAKey)
+    }
+    fn serialize_tuple(
+        self,
+        _len: usize,
+    ) -> Result<NotAKey, IqInternalError> {
+        Err(IqInternalError::NotAKey)
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<NotAKey, IqInternalError> {
+        Err(IqInternalError::NotAKey)
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<NotAKey, IqInternalError> {
+        Err(IqInternalError::NotAKey)
+    }
+    fn serialize_map(
+        self,
+        _len: Option<usize>,
+    ) -> Result<NotAKey, IqInternalError> {
+        Err(IqInternalError::NotAKey)
+    }
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<NotAKey, IqInternalError> {
+        Err(IqInternalError::NotAKey)
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<NotAKey, IqInternalError> {
+        Err(IqInternalError::NotAKey)
+    }
+}
+
+#[test]
+fn test_map_key() {
+    #[derive(Serialize)]
+    enum Color {
+        Red,
+        Custom(u8),
+    }
+    #[derive(Serialize)]
+    struct Id(u32);
+    fn rendered<K: Serialize>(k: K) -> Option<String> {
+        let mut key = MapKey::default();
+        key.render(&k).unwrap().then(|| key.as_str().to_string())
+    }
+    assert_eq!(rendered("a\"b.c"), Some("a\"b.c".to_string()));
+    assert_eq!(rendered('é'), Some("é".to_string()));
+    assert_eq!(rendered(-12i64), Some("-12".to_string()));
+    assert_eq!(rendered(1.5f32), Some("1.5".to_string()));
+    assert_eq!(rendered(false), Some("false".to_string()));
+    assert_eq!(rendered(Color::Red), Some("Red".to_string()));
+    assert_eq!(rendered(Color::Custom(3)), Some("3".to_string()));
+    assert_eq!(rendered(Id(7)), Some("7".to_string()));
+    assert_eq!(rendered(Some("x")), Some("x".to_string()));
+    assert_eq!(rendered((1, "a")), Some(r#"[1,"a"]"#.to_string()));
+    assert_eq!(rendered(()), None);
+    let mut key = MapKey::default();
+    let is = |key: &MapKey, path_key: &str, key_matching| {
+        key.matches(&PathKey::new(path_key), key_matching)
+    };
+    assert!(key.render("a").unwrap());
+    assert!(is(&key, "a", KeyMatching::Exact));
+    assert!(!is(&key, "A", KeyMatching::Exact));
+    assert!(is(&key, "A", KeyMatching::AsciiCaseInsensitive));
+    assert!(key.render("\"a\"").unwrap());
+    assert!(is(&key, "\"a\"", KeyMatching::Exact));
+    assert!(!is(&key, "a", KeyMatching::Exact));
+    assert!(key.render(&12u8).unwrap());
+    assert!(is(&key, "012", KeyMatching::Exact));
+    assert!(!is(&key, "12.0", KeyMatching::Exact));
+    assert!(key.render(&(1, ("a", -2))).unwrap());
+    assert!(is(&key, "(1,(a,-2))", KeyMatching::Exact));
+    assert!(is(&key, r#"[1,["a",-2]]"#, KeyMatching::Exact));
+    assert!(!is(&key, "(1,a,-2)", KeyMatching::Exact));
+    assert!(!key.render(&()).unwrap());
+    assert!(!is(&key, "", KeyMatching::Exact));
+}
@@ -0,0 +1,92 @@
+/// How the keys of a path are compared with struct fields and map keys
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum KeyMatching {
+    /// Keys must be equal
+    #[default]
+    Exact,
+    /// ASCII letters are compared case-insensitively, so that `userid`
+    /// matches `userId`
+    AsciiCaseInsensitive,
+    /// ASCII case, `_` and `-` are ignored, so that `user_id` matches
+    /// `userId` and `user-id`
+    StyleInsensitive,
+}
+
+impl KeyMatching {
+    /// Tell whether the key of a path designates the given key
+    pub fn matches(
+        self,
+        path_key: &str,
+        key: &str,
+    ) -> bool {
+        match self {
+            Self::Exact => path_key == key,
+            Self::AsciiCaseInsensitive => path_key.eq_ignore_ascii_case(key),
+            Self::StyleInsensitive => {
+                let significant = |c: &char| *c != '_' && *c != '-';
+                let mut a = path_key.chars().filter(significant);
+                let mut b = key.chars().filter(significant);
+                loop {
+                    match (a.next(), b.next()) {
+                        (None, None) => return true,
+                        (Some(ca), Some(cb)) if ca.eq_ignore_ascii_case(&cb) => {}
+                        _ => return false,
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Options of the `extract_with_options` family of functions
+///
+/// ```
+/// use iq::*;
+/// use std::collections::HashMap;
+///
+/// let config: HashMap<&str, HashMap<&str, u32>> = [
+///     ("maxConnections", [("per-host", 8)].into_iter().collect()),
+/// ].into_iter().collect();
+/// let options = IqOptions::new().with_key_matching(KeyMatching::StyleInsensitive);
+/// assert_eq!(
+///     extract_primitive_with_options(&config, "max_connections.per_host", &options),
+///     Some("8".to_string()),
+/// );
+/// assert_eq!(extract_primitive(&config, "max_connections.per_host"), None);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct IqOptions {
+    /// How the keys of the path are compared with struct fields
+    /// and map keys
+    pub key_matching: KeyMatching,
+}
+
+impl IqOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_key_matching(
+        mut self,
+        key_matching: KeyMatching,
+    ) -> Self {
+        self.key_matching = key_matching;
+        self
+    }
+}
+
+#[test]
+fn test_key_matching() {
+    let exact = KeyMatching::Exact;
+    let case = KeyMatching::AsciiCaseInsensitive;
+    let style = KeyMatching::StyleInsensitive;
+    assert!(exact.matches("userId", "userId"));
+    assert!(!exact.matches("userid", "userId"));
+    assert!(case.matches("userid", "userId"));
+    assert!(case.matches("USERID", "userId"));
+    assert!(!case.matches("user_id", "userId"));
+    assert!(style.matches("user_id", "userId"));
+    assert!(style.matches("user-id", "USER_ID"));
+    assert!(style.matches("userid", "__user-id"));
+    assert!(!style.matches("user_ids", "userId"));
+    assert!(!style.matches("Éa", "éa"));
+}
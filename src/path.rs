@@ -1,6 +1,32 @@
 /// A path defining a deep destination into a value.
 ///
 /// Searching with an empty path will always return None.
+///
+/// A key of the path is compared exactly with the keys of the maps it
+/// goes through, which are rendered without quotes nor escaping:
+/// - a string or char key is taken as is
+/// - a bool or number key is written as by `extract_primitive` (`true`, `42`)
+/// - a unit variant key is its name (as renamed by serde)
+/// - an option, newtype struct or newtype variant key is rendered as its content
+///
+/// An integer map key is compared numerically, so that `01` matches the `1` key.
+///
+/// A composite map key (eg a tuple or a struct) is matched by a tuple like
+/// `(3,4)` or by JSON in brackets, like `[{"x":3,"y":4}]`, and compared
+/// structurally: `grid.(3,4).value`, `grid[{"x":3,"y":4}].value`.
+/// Dots inside parentheses, brackets or braces don't separate keys, and
+/// a key in brackets doesn't need to be preceded by a dot (`items[2]` is
+/// the same as `items.2`).
+///
+/// Other map keys (eg unit) are only matched by a `*` pattern, in the
+/// functions supporting patterns.
+///
+/// In the functions supporting patterns (eg `extract_all`, `Project`,
+/// `Redact`), a `*` key matches any key or index, a key containing `*` or
+/// `?` is a glob (`cpu_*`), and, with the `regex` feature, a key between
+/// slashes is a regular expression (`/^cpu_\d+$/`), in which dots don't
+/// separate keys. A key between slashes which isn't a valid regular
+/// expression (eg `/[/`) isn't an error: it's a plain key, matched exactly.
 pub trait IqPath {
     fn keys(&self) -> impl Iterator<Item = &str>;
 
@@ -12,14 +38,14 @@ pub trait IqPath {
     }
 }
 
-impl IqPath for &Vec<String> {
+impl<P: IqPath + ?Sized> IqPath for &P {
     fn keys(&self) -> impl Iterator<Item = &str> {
-        self.iter().map(|s| s.as_str())
+        (**self).keys()
     }
 }
-impl IqPath for &Vec<&str> {
+impl IqPath for &Vec<String> {
     fn keys(&self) -> impl Iterator<Item = &str> {
-        self.iter().copied()
+        self.iter().map(|s| s.as_str())
     }
 }
 impl IqPath for &[String] {
@@ -44,6 +70,202 @@ impl IqPath for Vec<&str> {
 }
 impl IqPath for &str {
     fn keys(&self) -> impl Iterator<Item = &str> {
-        self.split('.')
+        PathKeys { rest: Some(self) }
+    }
+}
+
+/// The keys of a path given as a string
+struct PathKeys<'p> {
+    rest: Option<&'p str>,
+}
+impl<'p> PathKeys<'p> {
+    /// Return the index of the `]` closing a key in brackets, if any
+    fn closing_bracket(s: &str) -> Option<usize> {
+        let mut nesting = Nesting {
+            depth: 1,
+            ..Default::default()
+        };
+        for (i, c) in s.char_indices() {
+            nesting.push(c);
+            if nesting.depth == 0 {
+                return Some(i);
+            }
+        }
+        None
+    }
+    fn bracketed_key(
+        &mut self,
+        s: &'p str,
+    ) -> Option<&'p str> {
+        let inner = s.strip_prefix('[')?;
+        let end = Self::closing_bracket(inner)?;
+        let after = &inner[end + 1..];
+        self.rest = if after.is_empty() {
+            None
+        } else {
+            Some(after.strip_prefix('.').unwrap_or(after))
+        };
+        Some(&inner[..end])
+    }
+    /// Return the key, slashes included, if it's a regular expression,
+    /// in which dots don't separate keys
+    #[cfg(feature = "regex")]
+    fn regex_key(
+        &mut self,
+        s: &'p str,
+    ) -> Option<&'p str> {
+        let inner = s.strip_prefix('/')?;
+        let mut escaped = false;
+        for (i, c) in inner.char_indices() {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '/' {
+                let end = i + 2; // in s
+                let after = &s[end..];
+                if after.is_empty() {
+                    self.rest = None;
+                } else if let Some(rest) = after.strip_prefix('.') {
+                    self.rest = Some(rest);
+                } else if after.starts_with('[') {
+                    self.rest = Some(after);
+                } else {
+                    continue;
+                }
+                return Some(&s[..end]);
+            }
+        }
+        None
+    }
+}
+impl<'p> Iterator for PathKeys<'p> {
+    type Item = &'p str;
+    fn next(&mut self) -> Option<&'p str> {
+        let s = self.rest?;
+        if let Some(key) = self.bracketed_key(s) {
+            return Some(key);
+        }
+        #[cfg(feature = "regex")]
+        if let Some(key) = self.regex_key(s) {
+            return Some(key);
+        }
+        let mut nesting = Nesting::default();
+        for (i, c) in s.char_indices() {
+            if nesting.is_top_level() {
+                if c == '.' {
+                    self.rest = Some(&s[i + 1..]);
+                    return Some(&s[..i]);
+                }
+                if c == '[' && i > 0 && Self::closing_bracket(&s[i + 1..]).is_some() {
+                    self.rest = Some(&s[i..]);
+                    return Some(&s[..i]);
+                }
+            }
+            nesting.push(c);
+        }
+        if nesting.is_top_level() {
+            self.rest = None;
+            return Some(s);
+        }
+        // unbalanced parentheses, brackets or braces are ignored
+        match s.split_once('.') {
+            Some((key, rest)) => {
+                self.rest = Some(rest);
+                Some(key)
+            }
+            None => {
+                self.rest = None;
+                Some(s)
+            }
+        }
+    }
+}
+
+/// The nesting state of a string, where dots and commas inside
+/// parentheses, brackets, braces, or inside double quotes in those,
+/// aren't separators
+#[derive(Debug, Default)]
+pub(crate) struct Nesting {
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+impl Nesting {
+    pub fn is_top_level(&self) -> bool {
+        self.depth == 0 && !self.in_string
+    }
+    pub fn push(
+        &mut self,
+        c: char,
+    ) {
+        if self.in_string {
+            if self.escaped {
+                self.escaped = false;
+            } else if c == '\\' {
+                self.escaped = true;
+            } else if c == '"' {
+                self.in_string = false;
+            }
+            return;
+        }
+        match c {
+            '"' if self.depth > 0 => self.in_string = true,
+            '(' | '[' | '{' => self.depth += 1,
+            ')' | ']' | '}' if self.depth > 0 => self.depth -= 1,
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn test_path_keys() {
+    let check = |path: &str, expected: &[&str]| {
+        assert_eq!(path.keys().collect::<Vec<_>>(), expected);
+    };
+    check("", &[""]);
+    check("a.b.c", &["a", "b", "c"]);
+    check("a..b.", &["a", "", "b", ""]);
+    check("grid.(3,4).value", &["grid", "(3,4)", "value"]);
+    check("grid.(1.5,a.b)", &["grid", "(1.5,a.b)"]);
+    check(
+        r#"grid[{"x":3,"y":4}].value"#,
+        &["grid", r#"{"x":3,"y":4}"#, "value"],
+    );
+    check(r#"m[{"a]":"b.c"}]"#, &["m", r#"{"a]":"b.c"}"#]);
+    check(r#"m["a]"]"#, &["m", r#""a]""#]);
+    check("items[2][0]", &["items", "2", "0"]);
+    check("a(.b", &["a(", "b"]);
+    check("a[.b", &["a[", "b"]);
+    check(r"a./b/c/.d", &["a", "/b/c/", "d"]);
+    check("a./b", &["a", "/b"]);
+    check("a./", &["a", "/"]);
+    // dots only don't separate keys in a regular expression when the
+    // feature is enabled
+    #[cfg(feature = "regex")]
+    check(r"a./b.c/.d", &["a", "/b.c/", "d"]);
+    #[cfg(not(feature = "regex"))]
+    check(r"a./b.c/.d", &["a", "/b", "c/", "d"]);
+}
+
+#[test]
+fn test_keys_with_special_chars() {
+    use {
+        crate::extract_primitive,
+        std::collections::HashMap,
+    };
+    let keys = ["a/b", "/c/", "a(b", "[c", "f(x)", "{d}", "e}"];
+    let map: HashMap<&str, usize> = keys.iter().enumerate().map(|(i, k)| (*k, i)).collect();
+    for (i, key) in keys.iter().enumerate() {
+        assert_eq!(
+            extract_primitive(&map, *key),
+            Some(i.to_string()),
+            "key {:?}",
+            key
+        );
     }
+    // a key in brackets is an index, as in `items[2]`
+    let map: HashMap<&str, usize> = [("g[0]", 1)].into_iter().collect();
+    assert_eq!(extract_primitive(&map, "g[0]"), None);
+    assert_eq!(extract_primitive(&map, &["g[0]"]), Some("1".to_string()));
 }
@@ -0,0 +1,300 @@
+use crate::{
+    map_key::{
+        MapKey,
+        PathKey,
+        composite_key,
+    },
+    *,
+};
+
+/// The key of a child of a value, to be matched by a segment
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ChildKey<'k> {
+    /// The name of a struct field
+    Field(&'k str),
+    /// The index of an item in a sequence, tuple, etc.
+    Index(usize),
+    /// A map key
+    Map(&'k MapKey),
+}
+
+/// A part of a path pattern, matching one key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Segment {
+    /// `*`: any key or index
+    Any,
+    /// a key which must be equal
+    Key(PathKey),
+    /// a glob, eg `cpu_*`, where `*` matches any sequence of chars
+    /// and `?` any char
+    Glob(String),
+    /// a regular expression between slashes, eg `/^cpu_\d+$/`
+    #[cfg(feature = "regex")]
+    Regex(KeyRegex),
+}
+
+/// A regular expression matching keys
+#[cfg(feature = "regex")]
+#[derive(Debug, Clone)]
+pub(crate) struct KeyRegex(lazy_regex::Regex);
+#[cfg(feature = "regex")]
+impl PartialEq for KeyRegex {
+    fn eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+#[cfg(feature = "regex")]
+impl Eq for KeyRegex {}
+
+impl Segment {
+    pub fn new(token: &str) -> Self {
+        if token == "*" {
+            return Self::Any;
+        }
+        #[cfg(feature = "regex")]
+        if let Some(regex) = token.strip_prefix('/').and_then(|t| t.strip_suffix('/')) {
+            // an invalid regular expression is a plain key, not a glob
+            return match lazy_regex::Regex::new(regex) {
+                Ok(regex) => Self::Regex(KeyRegex(regex)),
+                Err(_) => Self::Key(PathKey::new(token)),
+            };
+        }
+        if token.contains(['*', '?']) && composite_key(token).is_none() {
+            return Self::Glob(token.to_string());
+        }
+        Self::Key(PathKey::new(token))
+    }
+    pub fn matches(
+        &self,
+        key: ChildKey<'_>,
+    ) -> bool {
+        match (self, key) {
+            (Self::Any, _) => true,
+            (Self::Key(k), ChildKey::Field(name)) => k.name() == name,
+            (Self::Key(k), ChildKey::Index(idx)) => k.index() == Some(idx),
+            (Self::Key(k), ChildKey::Map(map_key)) => map_key.matches(k, KeyMatching::Exact),
+            (_, ChildKey::Field(name)) => self.matches_text(name),
+            (_, ChildKey::Index(idx)) => self.matches_text(&idx.to_string()),
+            (_, ChildKey::Map(map_key)) => {
+                map_key.is_rendered() && self.matches_text(map_key.as_str())
+            }
+        }
+    }
+    /// Tell whether a glob or regex segment matches the text of a key
+    fn matches_text(
+        &self,
+        text: &str,
+    ) -> bool {
+        match self {
+            Self::Glob(glob) => glob_matches(glob, text),
+            #[cfg(feature = "regex")]
+            Self::Regex(KeyRegex(regex)) => regex.is_match(text),
+            _ => false,
+        }
+    }
+}
+
+/// Tell whether the text matches the glob, where `*` matches any
+/// sequence of chars and `?` any char
+fn glob_matches(
+    glob: &str,
+    text: &str,
+) -> bool {
+    let (mut gi, mut ti) = (0, 0);
+    // where to resume after the last star: index in glob, index in text
+    let mut resume: Option<(usize, usize)> = None;
+    while let Some(tc) = text[ti..].chars().next() {
+        match glob[gi..].chars().next() {
+            Some('*') => {
+                gi += 1;
+                resume = Some((gi, ti));
+                continue;
+            }
+            Some(gc) if gc == '?' || gc == tc => {
+                gi += gc.len_utf8();
+                ti += tc.len_utf8();
+                continue;
+            }
+            _ => {}
+        }
+        // mismatch: let the last star absorb one more char
+        let Some((star_gi, star_ti)) = resume else {
+            return false;
+        };
+        let skipped = text[star_ti..].chars().next().map_or(0, char::len_utf8);
+        resume = Some((star_gi, star_ti + skipped));
+        gi = star_gi;
+        ti = star_ti + skipped;
+    }
+    glob[gi..].chars().all(|c| c == '*')
+}
+
+fn literal_segments<P: IqPath>(path: &P) -> impl Iterator<Item = Segment> + '_ {
+    path.keys()
+        .filter(|k| !k.is_empty())
+        .map(|k| Segment::Key(PathKey::new(k)))
+}
+
+/// A path where some segments may be patterns, matching several paths
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PathPattern {
+    segments: Vec<Segment>,
+}
+
+impl PathPattern {
+    pub fn new<P: IqPath>(path: P) -> Self {
+        let segments = path
+            .keys()
+            .filter(|k| !k.is_empty())
+            .map(Segment::new)
+            .collect();
+        Self { segments }
+    }
+    /// Tell whether the given concrete path is matched by the pattern or
+    /// is below a path matched by the pattern
+    pub fn matches_prefix_of(
+        &self,
+        path: &[String],
+    ) -> bool {
+        self.segments.len() <= path.len()
+            && self
+                .segments
+                .iter()
+                .zip(path)
+                .all(|(segment, key)| segment.matches(ChildKey::Field(key)))
+    }
+}
+
+/// A set of path patterns, stored as a tree so that common prefixes
+/// are only checked once
+#[derive(Debug, Clone)]
+pub(crate) struct PatternTree {
+    nodes: Vec<PatternNode>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct PatternNode {
+    children: Vec<(Segment, usize)>,
+    terminal: bool,
+}
+
+impl PatternTree {
+    pub const ROOT: usize = 0;
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![PatternNode::default()],
+        }
+    }
+    /// Add a path pattern, return the index of its last node
+    pub fn add<P: IqPath>(
+        &mut self,
+        path: &P,
+    ) -> usize {
+        self.add_segments(path.keys().filter(|k| !k.is_empty()).map(Segment::new))
+    }
+    /// Add a path whose keys are all literal (a `*`, a glob or a regex
+    /// is just a key to match exactly), return the index of its last node
+    pub fn add_literal<P: IqPath>(
+        &mut self,
+        path: &P,
+    ) -> usize {
+        self.add_segments(literal_segments(path))
+    }
+    /// Add a pattern matching the children of the value at a literal path,
+    /// return the index of its last node
+    pub fn add_children_of<P: IqPath>(
+        &mut self,
+        path: &P,
+    ) -> usize {
+        self.add_segments(literal_segments(path).chain(std::iter::once(Segment::Any)))
+    }
+    fn add_segments(
+        &mut self,
+        segments: impl Iterator<Item = Segment>,
+    ) -> usize {
+        let mut node = Self::ROOT;
+        for segment in segments {
+            let existing = self.nodes[node]
+                .children
+                .iter()
+                .find(|(s, _)| *s == segment)
+                .map(|(_, child)| *child);
+            node = match existing {
+                Some(child) => child,
+                None => {
+                    let child = self.nodes.len();
+                    self.nodes.push(PatternNode::default());
+                    self.nodes[node].children.push((segment, child));
+                    child
+                }
+            };
+        }
+        self.nodes[node].terminal = true;
+        node
+    }
+    pub fn is_terminal(
+        &self,
+        node: usize,
+    ) -> bool {
+        self.nodes[node].terminal
+    }
+    pub fn has_children(
+        &self,
+        node: usize,
+    ) -> bool {
+        !self.nodes[node].children.is_empty()
+    }
+    /// Push the children of the given nodes which match the key
+    pub fn push_matching_children(
+        &self,
+        nodes: &[usize],
+        key: ChildKey<'_>,
+        dst: &mut Vec<usize>,
+    ) {
+        for &node in nodes {
+            for (segment, child) in &self.nodes[node].children {
+                if segment.matches(key) {
+                    dst.push(*child);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_glob() {
+    assert!(glob_matches("cpu_*", "cpu_"));
+    assert!(glob_matches("cpu_*", "cpu_12"));
+    assert!(!glob_matches("cpu_*", "gpu_12"));
+    assert!(glob_matches("*_load", "cpu_load"));
+    assert!(glob_matches("c*_*d", "cpu_load"));
+    assert!(!glob_matches("c*_*d", "cpu_loads"));
+    assert!(glob_matches("?é?", "aéb"));
+    assert!(!glob_matches("?", "ab"));
+    assert!(glob_matches("a**", "a"));
+}
+
+#[test]
+fn test_path_pattern() {
+    let path = |s: &str| s.iq_path();
+    let pattern = PathPattern::new("users.*.email");
+    assert!(pattern.matches_prefix_of(&path("users.3.email")));
+    assert!(pattern.matches_prefix_of(&path("users.john.email.domain")));
+    assert!(!pattern.matches_prefix_of(&path("users.3")));
+    assert!(!pattern.matches_prefix_of(&path("users.3.name")));
+    assert!(PathPattern::new("").matches_prefix_of(&path("users")));
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn test_regex_segment() {
+    assert!(matches!(Segment::new(r"/^cpu_\d+$/"), Segment::Regex(_)));
+    // an invalid regex is a plain key, even with glob chars
+    assert_eq!(Segment::new("/[/"), Segment::Key(PathKey::new("/[/")));
+    assert_eq!(Segment::new("/a*[/"), Segment::Key(PathKey::new("/a*[/")));
+    assert!(Segment::new("/a*[/").matches(ChildKey::Field("/a*[/")));
+    assert!(!Segment::new("/a*[/").matches(ChildKey::Field("/ab[/")));
+}
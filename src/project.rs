@@ -0,0 +1,161 @@
+use {
+    crate::{
+        pattern::{
+            ChildKey,
+            PatternTree,
+        },
+        rewrite::*,
+        *,
+    },
+    serde::{
+        Serialize,
+        Serializer,
+    },
+};
+
+/// A wrapper around a value, serializing only the selected paths
+/// (and the structure leading to them).
+///
+/// A `*` key in a path matches any key or index.
+///
+/// As the projection is done while serializing, there's no intermediate
+/// value built, and any serde serializer can be used.
+///
+/// ```
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Dog {
+///     name: &'static str,
+///     ears: u8,
+/// }
+/// #[derive(Serialize)]
+/// struct Car {
+///     engine: &'static str,
+///     driver: Dog,
+///     passengers: Vec<Dog>,
+/// }
+/// let car = Car {
+///     engine: "V8",
+///     driver: Dog { name: "Rex", ears: 2 },
+///     passengers: vec![Dog { name: "Laïka", ears: 2 }, Dog { name: "Roverandom", ears: 1 }],
+/// };
+/// let projection = iq::Project::new(&car, ["driver.name", "passengers.*.ears"]);
+/// assert_eq!(
+///     serde_json::to_string(&projection).unwrap(),
+///     r#"{"driver":{"name":"Rex"},"passengers":[{"ears":2},{"ears":1}]}"#,
+/// );
+/// ```
+pub struct Project<'v, T: ?Sized> {
+    value: &'v T,
+    selection: Selection,
+}
+
+struct Selection {
+    tree: PatternTree,
+}
+
+impl Rule for Selection {
+    fn verdict(
+        &self,
+        nodes: &[usize],
+        key: ChildKey<'_>,
+    ) -> Verdict {
+        let mut children = Vec::new();
+        self.tree.push_matching_children(nodes, key, &mut children);
+        if children.is_empty() {
+            Verdict::Drop
+        } else if children.iter().any(|&child| self.tree.is_terminal(child)) {
+            Verdict::Keep
+        } else {
+            Verdict::Enter(children)
+        }
+    }
+}
+
+impl<'v, T: ?Sized + Serialize> Project<'v, T> {
+    /// Wrap a value, selecting the given paths.
+    ///
+    /// An empty path selects the whole value.
+    pub fn new<I, P>(
+        value: &'v T,
+        paths: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: IqPath,
+    {
+        let mut tree = PatternTree::new();
+        for path in paths {
+            tree.add(&path);
+        }
+        Self {
+            value,
+            selection: Selection { tree },
+        }
+    }
+}
+
+impl<T: ?Sized + Serialize> Serialize for Project<'_, T> {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        if self.selection.tree.is_terminal(PatternTree::ROOT) {
+            return self.value.serialize(serializer);
+        }
+        Rewritten {
+            rule: &self.selection,
+            nodes: vec![PatternTree::ROOT],
+            value: self.value,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[test]
+fn test_project() {
+    use std::collections::BTreeMap;
+    #[derive(Serialize)]
+    enum Shape {
+        Circle { x: i32, y: i32, radius: u32 },
+        Dot(i32, i32),
+    }
+    #[derive(Serialize)]
+    struct Drawing {
+        name: String,
+        shapes: Vec<Shape>,
+        tags: BTreeMap<String, Option<(u8, u8)>>,
+    }
+    let drawing = Drawing {
+        name: "test".to_string(),
+        shapes: vec![
+            Shape::Circle {
+                x: 1,
+                y: 2,
+                radius: 3,
+            },
+            Shape::Dot(4, 5),
+        ],
+        tags: [("a".to_string(), Some((1, 2))), ("b".to_string(), None)]
+            .into_iter()
+            .collect(),
+    };
+    let json = |paths: &[&str]| {
+        serde_json::to_string(&Project::new(&drawing, paths.iter().copied())).unwrap()
+    };
+    assert_eq!(json(&["name"]), r#"{"name":"test"}"#);
+    assert_eq!(json(&["nothing"]), r#"{}"#);
+    assert_eq!(
+        json(&["shapes.*.x", "shapes.1.0"]),
+        r#"{"shapes":[{"Circle":{"x":1}},{"Dot":[4]}]}"#
+    );
+    assert_eq!(
+        json(&["tags.a.1", "tags.b"]),
+        r#"{"tags":{"a":[2],"b":null}}"#
+    );
+    assert_eq!(
+        json(&["", "name"]),
+        serde_json::to_string(&drawing).unwrap()
+    );
+}
@@ -0,0 +1,723 @@
+use {
+    crate::{
+        map_key::{
+            MapKey,
+            PathKey,
+        },
+        *,
+    },
+    serde::{
+        Deserialize,
+        Deserializer,
+        Serialize,
+        de::{
+            self,
+            DeserializeSeed,
+            EnumAccess,
+            IgnoredAny,
+            MapAccess,
+            SeqAccess,
+            VariantAccess,
+            Visitor,
+        },
+    },
+    serde_json::{
+        Value,
+        value::RawValue,
+    },
+    std::{
+        borrow::Cow,
+        fmt,
+    },
+};
+
+/// Query a JSON text, without deserializing it in Rust types, and return
+/// the raw JSON of the value at the path, as a slice of the text.
+///
+/// The JSON is parsed only until the value is found, branches which
+/// don't match the path being skipped without being deserialized.
+/// A JSON syntax error before the value is found is returned as error.
+///
+/// An empty path designates the whole value. Map keys are compared with
+/// the keys of the path as by `extract_primitive` (see [IqPath]).
+///
+/// ```
+/// let json = r#"{"id": 3, "dogs": [{"name": "Rex"}, {"name": "Laïka", "ears": 2}]}"#;
+/// assert_eq!(
+///     iq::query_json_str(json, "dogs.1").unwrap(),
+///     Some(r#"{"name": "Laïka", "ears": 2}"#),
+/// );
+/// assert_eq!(iq::query_json_str(json, "dogs.1.name").unwrap(), Some(r#""Laïka""#));
+/// assert_eq!(iq::query_json_str(json, "dogs.2").unwrap(), None);
+/// ```
+pub fn query_json_str<P: IqPath>(
+    json: &str,
+    path: P,
+) -> Result<Option<&str>, IqError> {
+    query_json_str_with_options(json, path, &IqOptions::default())
+}
+
+/// Query a JSON text with the given options, and return the raw JSON of
+/// the value at the path
+pub fn query_json_str_with_options<'j, P: IqPath>(
+    json: &'j str,
+    path: P,
+    options: &IqOptions,
+) -> Result<Option<&'j str>, IqError> {
+    let keys: Vec<&str> = path.keys().collect();
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    let found: Option<&RawValue> = seek(&mut deserializer, &keys, options.key_matching)?;
+    if found.is_none() {
+        deserializer.end()?; // checking there's nothing after the value
+    }
+    Ok(found.map(RawValue::get))
+}
+
+/// Query any self-describing serde deserializer (eg a YAML, TOML or
+/// CBOR one) and return the value at the path as a JSON value.
+///
+/// Branches which don't match the path are skipped without being
+/// deserialized, and the deserialization stops when the value is found.
+///
+/// As with `extract_json`, an enum variant (eg a tagged YAML value) is
+/// transparent: the path goes directly into its content. And the found
+/// value is written as by `extract_json` (`{"Variant": content}` for
+/// a variant, map keys rendered as strings).
+///
+/// ```
+/// let json = r#"{"level": "warn", "ctx": {"user": 7, "ips": ["10.0.0.1"]}}"#;
+/// let mut deserializer = serde_json::Deserializer::from_str(json);
+/// assert_eq!(
+///     iq::query_deserializer(&mut deserializer, "ctx.ips").unwrap(),
+///     Some(serde_json::json!(["10.0.0.1"])),
+/// );
+/// ```
+pub fn query_deserializer<'de, D, P>(
+    deserializer: D,
+    path: P,
+) -> Result<Option<serde_json::Value>, IqError>
+where
+    D: Deserializer<'de>,
+    P: IqPath,
+{
+    let found: Option<JsonValue> = query_deserializer_into(deserializer, path)?;
+    Ok(found.map(|json| json.0))
+}
+
+/// Query any self-describing serde deserializer and deserialize the
+/// value at the path into any type implementing `Deserialize`.
+///
+/// Return an error if the found value can't be deserialized in `V`.
+///
+/// ```
+/// let json = r#"{"level": "warn", "ctx": {"user": 7, "ips": ["10.0.0.1"]}}"#;
+/// let mut deserializer = serde_json::Deserializer::from_str(json);
+/// let user: Option<u32> = iq::query_deserializer_into(&mut deserializer, "ctx.user").unwrap();
+/// assert_eq!(user, Some(7));
+/// ```
+pub fn query_deserializer_into<'de, D, P, V>(
+    deserializer: D,
+    path: P,
+) -> Result<Option<V>, IqError>
+where
+    D: Deserializer<'de>,
+    P: IqPath,
+    V: Deserialize<'de>,
+{
+    query_deserializer_into_with_options(deserializer, path, &IqOptions::default())
+}
+
+/// Query any self-describing serde deserializer with the given options,
+/// and deserialize the value at the path into any type implementing
+/// `Deserialize`
+///
+/// ```
+/// use iq::{IqOptions, KeyMatching};
+///
+/// let json = r#"{"Ctx": {"UserId": 7}}"#;
+/// let mut deserializer = serde_json::Deserializer::from_str(json);
+/// let options = IqOptions::new().with_key_matching(KeyMatching::StyleInsensitive);
+/// let user: Option<u32> =
+///     iq::query_deserializer_into_with_options(&mut deserializer, "ctx.user_id", &options)
+///         .unwrap();
+/// assert_eq!(user, Some(7));
+/// ```
+pub fn query_deserializer_into_with_options<'de, D, P, V>(
+    deserializer: D,
+    path: P,
+    options: &IqOptions,
+) -> Result<Option<V>, IqError>
+where
+    D: Deserializer<'de>,
+    P: IqPath,
+    V: Deserialize<'de>,
+{
+    let keys: Vec<&str> = path.keys().collect();
+    seek(deserializer, &keys, options.key_matching).map_err(|e| IqError::Serde(e.to_string()))
+}
+
+/// A JSON value deserialized from any self-describing format, built as
+/// `extract_json` would write it: an enum variant is `{"Variant": content}`
+/// and map keys are rendered as strings
+struct JsonValue(Value);
+
+impl<'de> Deserialize<'de> for JsonValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(JsonVisitor)
+    }
+}
+
+struct JsonVisitor;
+
+macro_rules! visit_json {
+    ($($name:ident: $ty:ty,)*) => {
+        $(
+            fn $name<E: de::Error>(self, v: $ty) -> Result<JsonValue, E> {
+                Ok(JsonValue(v.into()))
+            }
+        )*
+    };
+}
+
+impl<'de> Visitor<'de> for JsonVisitor {
+    type Value = JsonValue;
+    fn expecting(
+        &self,
+        formatter: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        formatter.write_str("any value")
+    }
+    visit_json! {
+        visit_bool: bool,
+        visit_i64: i64,
+        visit_u64: u64,
+        visit_f64: f64,
+        visit_str: &str,
+        visit_string: String,
+        visit_bytes: &[u8],
+    }
+    fn visit_i128<E: de::Error>(
+        self,
+        v: i128,
+    ) -> Result<JsonValue, E> {
+        match (i64::try_from(v), u64::try_from(v)) {
+            (Ok(v), _) => Ok(JsonValue(v.into())),
+            (_, Ok(v)) => Ok(JsonValue(v.into())),
+            _ => Ok(JsonValue((v as f64).into())),
+        }
+    }
+    fn visit_u128<E: de::Error>(
+        self,
+        v: u128,
+    ) -> Result<JsonValue, E> {
+        match u64::try_from(v) {
+            Ok(v) => Ok(JsonValue(v.into())),
+            Err(_) => Ok(JsonValue((v as f64).into())),
+        }
+    }
+    fn visit_char<E: de::Error>(
+        self,
+        v: char,
+    ) -> Result<JsonValue, E> {
+        Ok(JsonValue(v.to_string().into()))
+    }
+    fn visit_none<E: de::Error>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue(Value::Null))
+    }
+    fn visit_unit<E: de::Error>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue(Value::Null))
+    }
+    fn visit_some<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<JsonValue, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<JsonValue, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+    fn visit_seq<A: SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> Result<JsonValue, A::Error> {
+        let mut items = Vec::new();
+        while let Some(JsonValue(item)) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(JsonValue(Value::Array(items)))
+    }
+    fn visit_map<A: MapAccess<'de>>(
+        self,
+        mut map: A,
+    ) -> Result<JsonValue, A::Error> {
+        let mut entries = serde_json::Map::new();
+        let mut map_key = MapKey::default();
+        while let Some(key) = map.next_key_seed(KeyText)? {
+            map_key.render(&key).map_err(de::Error::custom)?;
+            let JsonValue(value) = map.next_value()?;
+            entries.insert(map_key.as_str().to_string(), value);
+        }
+        Ok(JsonValue(Value::Object(entries)))
+    }
+    fn visit_enum<A: EnumAccess<'de>>(
+        self,
+        data: A,
+    ) -> Result<JsonValue, A::Error> {
+        let (variant, content): (String, _) = data.variant()?;
+        let JsonValue(content) = content.newtype_variant()?;
+        let mut entries = serde_json::Map::new();
+        entries.insert(variant, content);
+        Ok(JsonValue(Value::Object(entries)))
+    }
+}
+
+/// Go to the value at the path and deserialize it.
+///
+/// Return `Ok(None)` if the path isn't found.
+fn seek<'de, D, V>(
+    deserializer: D,
+    keys: &[&str],
+    key_matching: KeyMatching,
+) -> Result<Option<V>, D::Error>
+where
+    D: Deserializer<'de>,
+    V: Deserialize<'de>,
+{
+    let mut found = None;
+    let seeker = Seeker {
+        keys,
+        found: &mut found,
+        key_matching,
+    };
+    match seeker.deserialize(deserializer) {
+        Err(_) if found.is_some() => Ok(found), // the short-circuit error
+        Err(e) => Err(e),
+        Ok(()) => Ok(None),
+    }
+}
+
+/// The seed of the value at the current depth of the path.
+///
+/// When the value at the end of the path is found, it's deserialized,
+/// stored, and an error is returned to stop the deserialization.
+struct Seeker<'p, 'f, V> {
+    keys: &'p [&'p str],
+    found: &'f mut Option<V>,
+    key_matching: KeyMatching,
+}
+
+impl<'de, V: Deserialize<'de>> Seeker<'_, '_, V> {
+    /// The seeker of the child designated by the first key of the path
+    fn child(&mut self) -> Seeker<'_, '_, V> {
+        Seeker {
+            keys: &self.keys[1..],
+            found: &mut *self.found,
+            key_matching: self.key_matching,
+        }
+    }
+}
+
+impl<'de, V: Deserialize<'de>> DeserializeSeed<'de> for Seeker<'_, '_, V> {
+    type Value = ();
+    fn deserialize<D>(
+        self,
+        deserializer: D,
+    ) -> Result<(), D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if self.keys.is_empty() {
+            *self.found = Some(V::deserialize(deserializer)?);
+            return Err(de::Error::custom("IQ: Found"));
+        }
+        deserializer.deserialize_any(self)
+    }
+}
+
+/// Scalars are leaves: the path can't go further
+macro_rules! visit_leaf {
+    ($($name:ident: $ty:ty,)*) => {
+        $(
+            fn $name<E: de::Error>(self, _v: $ty) -> Result<(), E> {
+                Ok(())
+            }
+        )*
+    };
+}
+
+impl<'de, V: Deserialize<'de>> Visitor<'de> for Seeker<'_, '_, V> {
+    type Value = ();
+    fn expecting(
+        &self,
+        formatter: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        formatter.write_str("any value")
+    }
+    visit_leaf! {
+        visit_bool: bool,
+        visit_i64: i64,
+        visit_u64: u64,
+        visit_i128: i128,
+        visit_u128: u128,
+        visit_f64: f64,
+        visit_char: char,
+        visit_str: &str,
+        visit_bytes: &[u8],
+    }
+    fn visit_none<E: de::Error>(self) -> Result<(), E> {
+        Ok(())
+    }
+    fn visit_unit<E: de::Error>(self) -> Result<(), E> {
+        Ok(())
+    }
+    fn visit_some<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+    fn visit_seq<A: SeqAccess<'de>>(
+        mut self,
+        mut seq: A,
+    ) -> Result<(), A::Error> {
+        if let Ok(idx) = self.keys[0].parse::<usize>() {
+            for _ in 0..idx {
+                if seq.next_element::<IgnoredAny>()?.is_none() {
+                    return Ok(()); // out of bounds
+                }
+            }
+            seq.next_element_seed(self.child())?;
+        }
+        while seq.next_element::<IgnoredAny>()?.is_some() {}
+        Ok(())
+    }
+    fn visit_map<A: MapAccess<'de>>(
+        mut self,
+        mut map: A,
+    ) -> Result<(), A::Error> {
+        // the key of the path is parsed once for all entries
+        let path_key = PathKey::new(self.keys[0]);
+        let mut map_key = MapKey::default();
+        while let Some(key) = map.next_key_seed(KeyText)? {
+            let matches = match key {
+                Some(key) => {
+                    map_key.render(&key).map_err(de::Error::custom)?
+                        && map_key.matches(&path_key, self.key_matching)
+                }
+                None => false,
+            };
+            if matches {
+                map.next_value_seed(self.child())?;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(())
+    }
+    fn visit_enum<A: EnumAccess<'de>>(
+        self,
+        data: A,
+    ) -> Result<(), A::Error> {
+        // as with extract, the path goes into the content of the variant
+        let (_, variant) = data.variant::<IgnoredAny>()?;
+        variant.newtype_variant_seed(self)
+    }
+}
+
+/// A map key, as deserialized, rendered for comparison by a `MapKey`
+/// (so that it's matched as the key of a serialized map would be)
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Key<'de> {
+    Str(Cow<'de, str>),
+    Bool(bool),
+    Int(i128),
+    UInt(u128),
+    Float(f64),
+    Composite(Value),
+}
+
+/// The seed of a map key (a string is borrowed from the input when
+/// possible), None if it can't be matched by a path
+struct KeyText;
+
+impl<'de> DeserializeSeed<'de> for KeyText {
+    type Value = Option<Key<'de>>;
+    fn deserialize<D>(
+        self,
+        deserializer: D,
+    ) -> Result<Option<Key<'de>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+macro_rules! visit_key {
+    ($($name:ident: $ty:ty => $variant:ident,)*) => {
+        $(
+            fn $name<E: de::Error>(self, v: $ty) -> Result<Option<Key<'de>>, E> {
+                Ok(Some(Key::$variant(v.into())))
+            }
+        )*
+    };
+}
+
+impl<'de> Visitor<'de> for KeyText {
+    type Value = Option<Key<'de>>;
+    fn expecting(
+        &self,
+        formatter: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        formatter.write_str("a map key")
+    }
+    visit_key! {
+        visit_bool: bool => Bool,
+        visit_i64: i64 => Int,
+        visit_u64: u64 => Int,
+        visit_i128: i128 => Int,
+        visit_u128: u128 => UInt,
+        visit_f64: f64 => Float,
+        visit_borrowed_str: &'de str => Str,
+        visit_string: String => Str,
+    }
+    fn visit_char<E: de::Error>(
+        self,
+        v: char,
+    ) -> Result<Option<Key<'de>>, E> {
+        Ok(Some(Key::Str(Cow::Owned(v.to_string()))))
+    }
+    fn visit_str<E: de::Error>(
+        self,
+        v: &str,
+    ) -> Result<Option<Key<'de>>, E> {
+        Ok(Some(Key::Str(Cow::Owned(v.to_string()))))
+    }
+    fn visit_bytes<E: de::Error>(
+        self,
+        _v: &[u8],
+    ) -> Result<Option<Key<'de>>, E> {
+        Ok(None)
+    }
+    fn visit_none<E: de::Error>(self) -> Result<Option<Key<'de>>, E> {
+        Ok(None)
+    }
+    fn visit_unit<E: de::Error>(self) -> Result<Option<Key<'de>>, E> {
+        Ok(None)
+    }
+    fn visit_some<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Option<Key<'de>>, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Option<Key<'de>>, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+    fn visit_seq<A: SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> Result<Option<Key<'de>>, A::Error> {
+        let mut elements = Vec::new();
+        while let Some(element) = seq.next_element()? {
+            elements.push(element);
+        }
+        Ok(Some(Key::Composite(Value::Array(elements))))
+    }
+    fn visit_map<A: MapAccess<'de>>(
+        self,
+        mut map: A,
+    ) -> Result<Option<Key<'de>>, A::Error> {
+        let mut fields = serde_json::Map::new();
+        while let Some((name, value)) = map.next_entry()? {
+            fields.insert(name, value);
+        }
+        Ok(Some(Key::Composite(Value::Object(fields))))
+    }
+    fn visit_enum<A: EnumAccess<'de>>(
+        self,
+        data: A,
+    ) -> Result<Option<Key<'de>>, A::Error> {
+        // as a MapKey, a variant key is rendered as its content
+        let (_, variant) = data.variant::<IgnoredAny>()?;
+        variant.newtype_variant_seed(self)
+    }
+}
+
+#[test]
+fn test_query_json_str() {
+    let json = r#"
+        {
+            "name": "fleet",
+            "ships": [
+                { "name": "Nostromo", "crew": ["Ripley", "Dallas"], "cargo": null },
+                { "name": "Sulaco", "crew": [], "escape": { "pods": 2 } }
+            ],
+            "1": "one"
+        }
+    "#;
+    let q = |path| query_json_str(json, path).unwrap();
+    assert_eq!(q("name"), Some(r#""fleet""#));
+    assert_eq!(q("ships.0.crew.1"), Some(r#""Dallas""#));
+    assert_eq!(q("ships.0.cargo"), Some("null"));
+    assert_eq!(q("ships.1.escape"), Some(r#"{ "pods": 2 }"#));
+    assert_eq!(q("ships[1].escape.pods"), Some("2"));
+    assert_eq!(q("1"), Some(r#""one""#));
+    assert_eq!(q("ships.2"), None);
+    assert_eq!(q("ships.first"), None);
+    assert_eq!(q("name.first"), None);
+    assert_eq!(q("ships.1.crew.0"), None);
+    assert_eq!(q("nothing"), None);
+    assert!(q("").is_none());
+    assert!(
+        query_json_str(json, Vec::<&str>::new())
+            .unwrap()
+            .unwrap()
+            .ends_with('}')
+    );
+    // the text after the found value isn't parsed
+    assert_eq!(
+        query_json_str(r#"{"a": [1, 2], "b": }"#, "a.1").unwrap(),
+        Some("2")
+    );
+    assert!(query_json_str(r#"{"a": [1, 2], "b": }"#, "c").is_err());
+    assert!(query_json_str(r#"{"a": 1} 2"#, "b").is_err());
+    // keys are compared as is, or with the key matching of the options
+    let json = r#"{"\"a\"": 1, "maxConnections": 2}"#;
+    assert_eq!(query_json_str(json, "\"a\"").unwrap(), Some("1"));
+    assert_eq!(query_json_str(json, "a").unwrap(), None);
+    assert_eq!(query_json_str(json, "max_connections").unwrap(), None);
+    let options = IqOptions::new().with_key_matching(KeyMatching::StyleInsensitive);
+    assert_eq!(
+        query_json_str_with_options(json, "max_connections", &options).unwrap(),
+        Some("2")
+    );
+}
+
+#[test]
+fn test_query_deserializer() {
+    let json = r#"[{"id": 1, "tags": {"a": true}}, {"id": 2, "tags": {}}]"#;
+    let query = |path| {
+        let mut deserializer = serde_json::Deserializer::from_str(json);
+        query_deserializer(&mut deserializer, path).unwrap()
+    };
+    assert_eq!(query("1.id"), Some(serde_json::json!(2)));
+    assert_eq!(query("0.tags"), Some(serde_json::json!({"a": true})));
+    assert_eq!(query("1.tags.a"), None);
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    let wrong: Result<Option<u8>, _> = query_deserializer_into(&mut deserializer, "0.tags");
+    assert!(wrong.is_err());
+    #[cfg(feature = "yaml")]
+    {
+        let yaml = "level: warn\nctx:\n  user: 7\n  roles: [admin, ops]\n";
+        let role: Option<String> =
+            query_deserializer_into(serde_norway::Deserializer::from_str(yaml), "ctx.roles.1")
+                .unwrap();
+        assert_eq!(role.as_deref(), Some("ops"));
+        let ctx = query_deserializer(serde_norway::Deserializer::from_str(yaml), "ctx").unwrap();
+        assert_eq!(
+            ctx,
+            Some(serde_json::json!({"user": 7, "roles": ["admin", "ops"]}))
+        );
+        // map keys are matched as by extract: numerically, structurally,
+        // and the path goes into tagged values (ie enum variants)
+        let yaml = "8: eight\n? [3, 4]\n: !Dot [1, 2]\nshape: !Circle\n  radius: 3\n";
+        let query = |path| query_deserializer(serde_norway::Deserializer::from_str(yaml), path);
+        assert_eq!(query("08").unwrap(), Some(serde_json::json!("eight")));
+        assert_eq!(query("(3,4).1").unwrap(), Some(serde_json::json!(2)));
+        assert_eq!(query("[[3,4]].0").unwrap(), Some(serde_json::json!(1)));
+        assert_eq!(query("shape.radius").unwrap(), Some(serde_json::json!(3)));
+        assert_eq!(query("(3,5)").unwrap(), None);
+    }
+    #[cfg(feature = "msgpack")]
+    {
+        use std::collections::BTreeMap;
+        let record: BTreeMap<u32, (&str, f64)> = [(3, ("cpu", 0.5)), (8, ("mem", 0.25))]
+            .into_iter()
+            .collect();
+        let bytes = rmp_serde::to_vec(&record).unwrap();
+        let mut deserializer = rmp_serde::Deserializer::new(bytes.as_slice());
+        let mem: Option<f64> = query_deserializer_into(&mut deserializer, "8.1").unwrap();
+        assert_eq!(mem, Some(0.25));
+        let mut deserializer = rmp_serde::Deserializer::new(bytes.as_slice());
+        assert_eq!(query_deserializer(&mut deserializer, "4").unwrap(), None);
+    }
+}
+
+/// Querying the YAML of a value gives the same result as extracting
+/// from the value
+#[cfg(feature = "yaml")]
+#[test]
+fn test_query_deserializer_as_extract() {
+    use std::collections::BTreeMap;
+    #[derive(Serialize)]
+    enum Shape {
+        Circle { radius: f64 },
+        Dot(i32, i32),
+        Id(u8),
+        Unit,
+    }
+    #[derive(Serialize)]
+    struct Drawing {
+        name: &'static str,
+        by_id: BTreeMap<u32, Shape>,
+        by_origin: BTreeMap<(i8, i8), Vec<Shape>>,
+        flags: BTreeMap<bool, Option<u8>>,
+    }
+    let drawing = Drawing {
+        name: "test",
+        by_id: [
+            (8, Shape::Dot(1, 2)),
+            (10, Shape::Circle { radius: 1.5 }),
+            (12, Shape::Unit),
+        ]
+        .into_iter()
+        .collect(),
+        by_origin: [((3, -4), vec![Shape::Id(7), Shape::Unit])]
+            .into_iter()
+            .collect(),
+        flags: [(true, Some(1)), (false, None)].into_iter().collect(),
+    };
+    let paths = [
+        "name",
+        "by_id",
+        "by_id.8",
+        "by_id.08",
+        "by_id.8.1",
+        "by_id.8.Dot",
+        "by_id.10",
+        "by_id.10.radius",
+        "by_id.12",
+        "by_id.13",
+        "by_origin.(3,-4)",
+        "by_origin.(3,-4).0",
+        "by_origin.(3, -4).1",
+        "by_origin[[3,-4]].0",
+        "by_origin.(3,4)",
+        "flags",
+        "flags.true",
+        "flags.false",
+        "flags.1",
+    ];
+    let yaml = serde_norway::to_string(&drawing).unwrap();
+    for path in paths {
+        let queried =
+            query_deserializer(serde_norway::Deserializer::from_str(&yaml), path).unwrap();
+        let extracted =
+            extract_json(&drawing, path).map(|json| serde_json::from_str(&json).unwrap());
+        assert_eq!(queried, extracted, "path: {}", path);
+    }
+}
@@ -0,0 +1,201 @@
+use {
+    crate::{
+        errors::IqInternalError,
+        pattern::PatternTree,
+        walker::*,
+        *,
+    },
+    serde::Serialize,
+    std::ops::ControlFlow,
+};
+
+/// A set of paths, compiled once to be extracted from many values.
+///
+/// Paths sharing a prefix are followed together, and indices are
+/// parsed in advance. Keys are literal, as with `extract_primitive`:
+/// `*` or `cpu_*` only match a key with this exact name.
+///
+/// ```
+/// let queries = iq::QuerySet::new(["0", "1.1", "2"]);
+/// let records = vec![("a", (1, 2)), ("b", (3, 4))];
+/// let extracted: Vec<_> = records.iter().map(|r| queries.extract(r)).collect();
+/// assert_eq!(
+///     extracted,
+///     vec![
+///         vec![Some("a".to_string()), Some("2".to_string()), None],
+///         vec![Some("b".to_string()), Some("4".to_string()), None],
+///     ],
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct QuerySet {
+    tree: PatternTree,
+    /// the pattern node of each path
+    targets: Vec<usize>,
+}
+
+impl QuerySet {
+    pub fn new<I, P>(paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: IqPath,
+    {
+        let mut tree = PatternTree::new();
+        let targets = paths
+            .into_iter()
+            .map(|path| tree.add_literal(&path))
+            .collect();
+        Self { tree, targets }
+    }
+    /// Return the number of paths in the set
+    pub fn len(&self) -> usize {
+        self.targets.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+    /// Extract strings at all the paths of the set, with a given format.
+    ///
+    /// The returned vec has one entry per path of the set, with None
+    /// for the paths which weren't found.
+    pub fn extract_checked<T: Serialize>(
+        &self,
+        source: &T,
+        format: IqFormat,
+    ) -> Result<Vec<Option<String>>, IqError> {
+        if self.targets.is_empty() {
+            return Ok(Vec::new());
+        }
+        let collector = Collector {
+            format,
+            targets: &self.targets,
+            results: vec![None; self.targets.len()],
+            remaining: self.targets.len(),
+        };
+        match Walker::new(&self.tree, collector).walk(source) {
+            Ok(collector) => Ok(collector.results.into_iter().map(Option::flatten).collect()),
+            Err(IqInternalError::Message(msg)) => Err(IqError::Serde(msg)),
+            Err(IqInternalError::Json(err)) => Err(IqError::Json(err)),
+            Err(_) => Ok(vec![None; self.targets.len()]), // not expected
+        }
+    }
+    /// Extract strings at all the paths of the set, with a given format,
+    /// returning only Nones if the `Serialize` implementation fails
+    pub fn extract_strings<T: Serialize>(
+        &self,
+        source: &T,
+        format: IqFormat,
+    ) -> Vec<Option<String>> {
+        self.extract_checked(source, format)
+            .unwrap_or_else(|_| vec![None; self.targets.len()])
+    }
+    /// Extract "primitive" values at all the paths of the set
+    pub fn extract<T: Serialize>(
+        &self,
+        source: &T,
+    ) -> Vec<Option<String>> {
+        self.extract_strings(source, IqFormat::Primitive)
+    }
+    /// Extract values as JSON at all the paths of the set
+    pub fn extract_json<T: Serialize>(
+        &self,
+        source: &T,
+    ) -> Vec<Option<String>> {
+        self.extract_strings(source, IqFormat::Json)
+    }
+}
+
+/// The visitor of a `QuerySet` extraction, rendering the first value
+/// found for each path
+struct Collector<'q> {
+    format: IqFormat,
+    targets: &'q [usize],
+    /// for each path, None until a value is found, then the rendered value
+    results: Vec<Option<Option<String>>>,
+    remaining: usize,
+}
+impl Visit for Collector<'_> {
+    fn visit<T>(
+        &mut self,
+        node: usize,
+        _path: &[String],
+        value: &T,
+    ) -> Result<ControlFlow<()>, IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        for (idx, &target) in self.targets.iter().enumerate() {
+            if target != node || self.results[idx].is_some() {
+                continue;
+            }
+            self.results[idx] = Some(extract_string_checked(&value, Vec::new(), self.format)?);
+            self.remaining -= 1;
+        }
+        if self.remaining == 0 {
+            Ok(ControlFlow::Break(()))
+        } else {
+            Ok(ControlFlow::Continue(()))
+        }
+    }
+    fn needs_path(&self) -> bool {
+        false
+    }
+}
+
+#[test]
+fn test_query_set() {
+    use std::collections::HashMap;
+    #[derive(Serialize)]
+    struct Record {
+        id: u64,
+        tags: Vec<&'static str>,
+        attributes: HashMap<&'static str, f32>,
+    }
+    let records: Vec<Record> = (0..5)
+        .map(|id| Record {
+            id,
+            tags: vec!["a", "b", "c"],
+            attributes: [("weight", id as f32 / 2.0)].into_iter().collect(),
+        })
+        .collect();
+    let paths = ["id", "tags.02", "attributes.weight", "tags.3", "tags"];
+    let queries = QuerySet::new(paths);
+    assert_eq!(queries.len(), 5);
+    for record in &records {
+        assert_eq!(queries.extract(record), extract_many(record, &paths));
+        assert_eq!(
+            queries.extract_json(record),
+            paths
+                .iter()
+                .map(|path| extract_json(record, *path))
+                .collect::<Vec<_>>(),
+        );
+    }
+    assert_eq!(
+        queries.extract(&records[3]),
+        vec![
+            Some("3".to_string()),
+            Some("c".to_string()),
+            Some("1.5".to_string()),
+            None,
+            None,
+        ],
+    );
+
+    // keys aren't patterns
+    let stats: HashMap<&str, u8> = [("cpu_*", 1), ("cpu_0", 2), ("*", 3)].into_iter().collect();
+    let paths = ["cpu_*", "cpu_?", "*", "cpu_0"];
+    let queries = QuerySet::new(paths);
+    assert_eq!(
+        queries.extract(&stats),
+        vec![
+            Some("1".to_string()),
+            None,
+            Some("3".to_string()),
+            Some("2".to_string()),
+        ],
+    );
+    for (path, extracted) in paths.iter().zip(queries.extract(&stats)) {
+        assert_eq!(extracted, extract_primitive(&stats, *path));
+    }
+}
@@ -0,0 +1,307 @@
+use {
+    crate::{
+        pattern::{
+            ChildKey,
+            PatternTree,
+        },
+        rewrite::*,
+        *,
+    },
+    serde::{
+        Serialize,
+        Serializer,
+    },
+    std::fmt,
+};
+
+/// How a masked value is replaced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Redaction {
+    /// Replace the value with the given string
+    Placeholder(String),
+    /// Replace the value with as many `*` as there are chars in its
+    /// primitive representation (or `***` if it's not a primitive)
+    Stars,
+    /// Replace the value with the HMAC-SHA256, keyed with the given
+    /// secret key, of its JSON representation (in hexadecimal), which lets
+    /// you see whether two values are equal without seeing them.
+    ///
+    /// Without the key, a short or guessable value can't be found back
+    /// from its hash. Use the same key to compare redacted values.
+    ///
+    /// Needs the `redact-hash` feature.
+    #[cfg(feature = "redact-hash")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "redact-hash")))]
+    Hash(Vec<u8>),
+}
+
+impl Default for Redaction {
+    fn default() -> Self {
+        Self::Placeholder("***".to_string())
+    }
+}
+
+/// A wrapper around a value, serializing it with some paths masked.
+///
+/// A `*` key in a path matches any key or index.
+///
+/// A `Redact` can be serialized with any serde serializer. It also
+/// implements `Display`, which writes the redacted value as JSON.
+///
+/// ```
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct User {
+///     name: &'static str,
+///     email: &'static str,
+/// }
+/// #[derive(Serialize)]
+/// struct Config {
+///     db_password: &'static str,
+///     users: Vec<User>,
+/// }
+/// let config = Config {
+///     db_password: "secret",
+///     users: vec![User { name: "Alice", email: "alice@example.com" }],
+/// };
+/// let redacted = iq::Redact::new(&config)
+///     .mask("db_password")
+///     .mask_with("users.*.email", iq::Redaction::Stars);
+/// assert_eq!(
+///     redacted.to_string(),
+///     r#"{"db_password":"***","users":[{"name":"Alice","email":"*****************"}]}"#,
+/// );
+/// ```
+pub struct Redact<'v, T: ?Sized> {
+    value: &'v T,
+    masks: Masks,
+}
+
+struct Masks {
+    tree: PatternTree,
+    redactions: Vec<(usize, Redaction)>,
+}
+
+impl Masks {
+    fn redaction(
+        &self,
+        node: usize,
+    ) -> &Redaction {
+        self.redactions
+            .iter()
+            .rev()
+            .find(|(n, _)| *n == node)
+            .map(|(_, redaction)| redaction)
+            .expect("a masked node has a redaction")
+    }
+}
+
+impl Rule for Masks {
+    fn verdict(
+        &self,
+        nodes: &[usize],
+        key: ChildKey<'_>,
+    ) -> Verdict {
+        let mut children = Vec::new();
+        self.tree.push_matching_children(nodes, key, &mut children);
+        if let Some(&node) = children.iter().find(|&&c| self.tree.is_terminal(c)) {
+            Verdict::Mask(node)
+        } else if children.is_empty() {
+            Verdict::Keep
+        } else {
+            Verdict::Enter(children)
+        }
+    }
+    fn may_drop(&self) -> bool {
+        false
+    }
+    fn mask<T, S>(
+        &self,
+        node: usize,
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        T: ?Sized + Serialize,
+        S: Serializer,
+    {
+        match self.redaction(node) {
+            Redaction::Placeholder(placeholder) => serializer.serialize_str(placeholder),
+            Redaction::Stars => {
+                let len = extract_primitive(&value, Vec::new()).map_or(3, |s| s.chars().count());
+                serializer.serialize_str(&"*".repeat(len))
+            }
+            #[cfg(feature = "redact-hash")]
+            Redaction::Hash(key) => {
+                use {
+                    serde::ser::Error,
+                    std::fmt::Write,
+                };
+                let json = serde_json::to_vec(value).map_err(S::Error::custom)?;
+                let mut hex = String::with_capacity(64);
+                for byte in hmac_sha256::HMAC::mac(json, key) {
+                    write!(hex, "{:02x}", byte).map_err(S::Error::custom)?;
+                }
+                serializer.serialize_str(&hex)
+            }
+        }
+    }
+}
+
+impl<'v, T: ?Sized + Serialize> Redact<'v, T> {
+    pub fn new(value: &'v T) -> Self {
+        Self {
+            value,
+            masks: Masks {
+                tree: PatternTree::new(),
+                redactions: Vec::new(),
+            },
+        }
+    }
+    /// Mask the values at the given path with the default redaction,
+    /// which is a `"***"` placeholder
+    pub fn mask<P: IqPath>(
+        self,
+        path: P,
+    ) -> Self {
+        self.mask_with(path, Redaction::default())
+    }
+    /// Mask the values at the given path with the given redaction
+    pub fn mask_with<P: IqPath>(
+        mut self,
+        path: P,
+        redaction: Redaction,
+    ) -> Self {
+        let node = self.masks.tree.add(&path);
+        self.masks.redactions.push((node, redaction));
+        self
+    }
+}
+
+impl<T: ?Sized + Serialize> Serialize for Redact<'_, T> {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        if self.masks.tree.is_terminal(PatternTree::ROOT) {
+            return self.masks.mask(PatternTree::ROOT, self.value, serializer);
+        }
+        Rewritten {
+            rule: &self.masks,
+            nodes: vec![PatternTree::ROOT],
+            value: self.value,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<T: ?Sized + Serialize> fmt::Display for Redact<'_, T> {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        let json = serde_json::to_string(self).map_err(|_| fmt::Error)?;
+        f.write_str(&json)
+    }
+}
+
+#[test]
+fn test_redact() {
+    use std::collections::HashMap;
+    #[derive(Serialize)]
+    struct Db {
+        host: String,
+        password: String,
+        port: u16,
+    }
+    #[derive(Serialize)]
+    struct Config {
+        db: Db,
+        tokens: HashMap<String, Vec<String>>,
+    }
+    let config = Config {
+        db: Db {
+            host: "localhost".to_string(),
+            password: "hunter2".to_string(),
+            port: 5432,
+        },
+        tokens: [(
+            "alice".to_string(),
+            vec!["t1".to_string(), "t2".to_string()],
+        )]
+        .into_iter()
+        .collect(),
+    };
+    let redacted = Redact::new(&config)
+        .mask("db.password")
+        .mask_with("db.port", Redaction::Stars)
+        .mask_with("tokens.*.1", Redaction::Placeholder("<hidden>".to_string()));
+    assert_eq!(
+        redacted.to_string(),
+        r#"{"db":{"host":"localhost","password":"***","port":"****"},"tokens":{"alice":["t1","<hidden>"]}}"#,
+    );
+    assert_eq!(Redact::new(&config).mask("").to_string(), r#""***""#);
+}
+
+#[cfg(feature = "redact-hash")]
+#[test]
+fn test_redact_hash() {
+    #[derive(Serialize)]
+    struct Db {
+        host: &'static str,
+        port: u16,
+    }
+    let db = Db {
+        host: "localhost",
+        port: 5432,
+    };
+    let hash = |key: &str| {
+        let hashed = Redact::new(&db).mask_with("host", Redaction::Hash(key.into()));
+        serde_json::to_value(&hashed).unwrap()["host"].clone()
+    };
+    let hashed = hash("key");
+    assert_eq!(hashed.as_str().unwrap().len(), 64);
+    // the same value always has the same hash with the same key
+    assert_eq!(hash("key"), hashed);
+    assert_ne!(hash("other key"), hashed);
+    // as `printf 5432 | openssl dgst -sha256 -hmac key`
+    let port = Redact::new(&db).mask_with("port", Redaction::Hash(b"key".to_vec()));
+    assert_eq!(
+        serde_json::to_value(&port).unwrap()["port"],
+        "462697851e1614c08e2bdf1d0334ac2150e4bfefe846337f3288512484845c85",
+    );
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+fn test_redact_binary() {
+    use std::net::{
+        IpAddr,
+        Ipv4Addr,
+    };
+    #[derive(Serialize)]
+    struct Server {
+        name: &'static str,
+        addrs: Vec<IpAddr>,
+    }
+    let server = Server {
+        name: "db",
+        addrs: vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))],
+    };
+    let cbor = |value: &dyn erased_serde::Serialize| {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(value, &mut bytes).unwrap();
+        bytes
+    };
+    // the addresses are rewritten, but serialized as without the
+    // rewriting (ie not as strings, as CBOR isn't human readable)
+    let redacted = Redact::new(&server).mask("addrs.*.secret");
+    assert_eq!(cbor(&redacted), cbor(&server));
+    let redacted = Redact::new(&server).mask("name");
+    let expected = Server {
+        name: "***",
+        addrs: server.addrs.clone(),
+    };
+    assert_eq!(cbor(&redacted), cbor(&expected));
+}
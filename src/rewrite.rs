@@ -0,0 +1,973 @@
+use {
+    crate::{
+        errors::IqInternalError,
+        map_key::MapKey,
+        pattern::ChildKey,
+    },
+    serde::{
+        Serialize,
+        Serializer,
+        ser,
+    },
+};
+
+/// What to do with a value, depending on its path
+pub(crate) enum Verdict {
+    /// Don't serialize the value (nor its key)
+    Drop,
+    /// Serialize the value unchanged
+    Keep,
+    /// Serialize the value, applying the rule to its children
+    /// with the given pattern nodes
+    Enter(Vec<usize>),
+    /// Serialize a replacement of the value, as defined by the
+    /// rule for the given pattern node
+    Mask(usize),
+}
+
+/// A rule deciding, for the children of a value, whether they're kept
+/// or dropped, or whether the rule must be applied to their own children.
+pub(crate) trait Rule {
+    fn verdict(
+        &self,
+        nodes: &[usize],
+        key: ChildKey<'_>,
+    ) -> Verdict;
+    /// Whether the verdict may be `Drop`, in which case the entries
+    /// must be counted before being serialized
+    fn may_drop(&self) -> bool {
+        true
+    }
+    /// Serialize the replacement of a value whose verdict is `Mask`
+    fn mask<T, S>(
+        &self,
+        _node: usize,
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        T: ?Sized + Serialize,
+        S: Serializer,
+    {
+        value.serialize(serializer)
+    }
+}
+
+/// A value whose serialization is rewritten according to a rule.
+///
+/// When a child is dropped, the lengths given to the serializer are
+/// adjusted (this requires a preliminary counting pass over the entries of
+/// the value, without going deeper), so that the result is valid even for
+/// formats writing lengths.
+pub(crate) struct Rewritten<'r, 'v, R, T: ?Sized> {
+    pub rule: &'r R,
+    pub nodes: Vec<usize>,
+    pub value: &'v T,
+}
+impl<R, T> Serialize for Rewritten<'_, '_, R, T>
+where
+    R: Rule,
+    T: ?Sized + Serialize,
+{
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut len = None;
+        if self.rule.may_drop() {
+            let mut counter = Counter {
+                rule: self.rule,
+                nodes: &self.nodes,
+                idx: 0,
+                count: 0,
+                map_key: MapKey::default(),
+                human_readable: serializer.is_human_readable(),
+            };
+            len = match self.value.serialize(&mut counter) {
+                Err(IqInternalError::Count(count)) => Some(count),
+                Err(IqInternalError::NoCount) | Ok(()) => None,
+                Err(err) => return Err(ser::Error::custom(err)),
+            };
+        }
+        self.value.serialize(Rewriter {
+            rule: self.rule,
+            nodes: &self.nodes,
+            len,
+            inner: serializer,
+        })
+    }
+}
+
+/// A value replaced by its mask
+struct Masked<'r, 'v, R, T: ?Sized> {
+    rule: &'r R,
+    node: usize,
+    value: &'v T,
+}
+impl<R, T> Serialize for Masked<'_, '_, R, T>
+where
+    R: Rule,
+    T: ?Sized + Serialize,
+{
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        self.rule.mask(self.node, self.value, serializer)
+    }
+}
+
+/// The serializer adapter doing the work of [Rewritten]
+struct Rewriter<'a, R, S> {
+    rule: &'a R,
+    nodes: &'a [usize],
+    len: Option<usize>,
+    inner: S,
+}
+impl<'a, R: Rule, S: Serializer> Rewriter<'a, R, S> {
+    fn wrap<'v, T: ?Sized>(
+        &self,
+        value: &'v T,
+    ) -> Rewritten<'a, 'v, R, T> {
+        Rewritten {
+            rule: self.rule,
+            nodes: self.nodes.to_vec(),
+            value,
+        }
+    }
+}
+impl<'a, R: Rule, S: Serializer> Serializer for Rewriter<'a, R, S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = RewriteCompound<'a, R, S::SerializeSeq>;
+    type SerializeTuple = RewriteCompound<'a, R, S::SerializeTuple>;
+    type SerializeTupleStruct = RewriteCompound<'a, R, S::SerializeTupleStruct>;
+    type SerializeTupleVariant = RewriteCompound<'a, R, S::SerializeTupleVariant>;
+    type SerializeMap = RewriteCompound<'a, R, S::SerializeMap>;
+    type SerializeStruct = RewriteCompound<'a, R, S::SerializeStruct>;
+    type SerializeStructVariant = RewriteCompound<'a, R, S::SerializeStructVariant>;
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+    fn serialize_bool(
+        self,
+        v: bool,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_bool(v)
+    }
+    fn serialize_i8(
+        self,
+        v: i8,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_i8(v)
+    }
+    fn serialize_i16(
+        self,
+        v: i16,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_i16(v)
+    }
+    fn serialize_i32(
+        self,
+        v: i32,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_i32(v)
+    }
+    fn serialize_i64(
+        self,
+        v: i64,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_i64(v)
+    }
+    fn serialize_i128(
+        self,
+        v: i128,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_i128(v)
+    }
+    fn serialize_u8(
+        self,
+        v: u8,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_u8(v)
+    }
+    fn serialize_u16(
+        self,
+        v: u16,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_u16(v)
+    }
+    fn serialize_u32(
+        self,
+        v: u32,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_u32(v)
+    }
+    fn serialize_u64(
+        self,
+        v: u64,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_u64(v)
+    }
+    fn serialize_u128(
+        self,
+        v: u128,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_u128(v)
+    }
+    fn serialize_f32(
+        self,
+        v: f32,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_f32(v)
+    }
+    fn serialize_f64(
+        self,
+        v: f64,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_f64(v)
+    }
+    fn serialize_char(
+        self,
+        v: char,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_char(v)
+    }
+    fn serialize_str(
+        self,
+        v: &str,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_str(v)
+    }
+    fn serialize_bytes(
+        self,
+        v: &[u8],
+    ) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_bytes(v)
+    }
+    fn serialize_none(self) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_none()
+    }
+    fn serialize_some<T>(
+        self,
+        value: &T,
+    ) -> Result<S::Ok, S::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let value = self.wrap(value);
+        self.inner.serialize_some(&value)
+    }
+    fn serialize_unit(self) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_unit()
+    }
+    fn serialize_unit_struct(
+        self,
+        name: &'static str,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_unit_struct(name)
+    }
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner
+            .serialize_unit_variant(name, variant_index, variant)
+    }
+    fn serialize_newtype_struct<T>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<S::Ok, S::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let value = self.wrap(value);
+        self.inner.serialize_newtype_struct(name, &value)
+    }
+    fn serialize_newtype_variant<T>(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<S::Ok, S::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let value = self.wrap(value);
+        self.inner
+            .serialize_newtype_variant(name, variant_index, variant, &value)
+    }
+    fn serialize_seq(
+        self,
+        len: Option<usize>,
+    ) -> Result<Self::SerializeSeq, S::Error> {
+        let inner = self.inner.serialize_seq(self.len.or(len))?;
+        Ok(RewriteCompound::new(self.rule, self.nodes, inner))
+    }
+    fn serialize_tuple(
+        self,
+        len: usize,
+    ) -> Result<Self::SerializeTuple, S::Error> {
+        let inner = self.inner.serialize_tuple(self.len.unwrap_or(len))?;
+        Ok(RewriteCompound::new(self.rule, self.nodes, inner))
+    }
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, S::Error> {
+        let inner = self
+            .inner
+            .serialize_tuple_struct(name, self.len.unwrap_or(len))?;
+        Ok(RewriteCompound::new(self.rule, self.nodes, inner))
+    }
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, S::Error> {
+        let inner = self.inner.serialize_tuple_variant(
+            name,
+            variant_index,
+            variant,
+            self.len.unwrap_or(len),
+        )?;
+        Ok(RewriteCompound::new(self.rule, self.nodes, inner))
+    }
+    fn serialize_map(
+        self,
+        len: Option<usize>,
+    ) -> Result<Self::SerializeMap, S::Error> {
+        let inner = self.inner.serialize_map(self.len.or(len))?;
+        Ok(RewriteCompound::new(self.rule, self.nodes, inner))
+    }
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, S::Error> {
+        let inner = self.inner.serialize_struct(name, self.len.unwrap_or(len))?;
+        Ok(RewriteCompound::new(self.rule, self.nodes, inner))
+    }
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, S::Error> {
+        let inner = self.inner.serialize_struct_variant(
+            name,
+            variant_index,
+            variant,
+            self.len.unwrap_or(len),
+        )?;
+        Ok(RewriteCompound::new(self.rule, self.nodes, inner))
+    }
+}
+
+/// A compound (sequence, map, struct, etc.) being rewritten
+pub(crate) struct RewriteCompound<'a, R, C> {
+    rule: &'a R,
+    nodes: &'a [usize],
+    inner: C,
+    idx: usize,
+    map_key: MapKey,
+    next_map_value: Option<Verdict>,
+}
+impl<'a, R: Rule, C> RewriteCompound<'a, R, C> {
+    fn new(
+        rule: &'a R,
+        nodes: &'a [usize],
+        inner: C,
+    ) -> Self {
+        Self {
+            rule,
+            nodes,
+            inner,
+            idx: 0,
+            map_key: MapKey::default(),
+            next_map_value: None,
+        }
+    }
+    fn verdict(
+        &self,
+        key: ChildKey<'_>,
+    ) -> Verdict {
+        self.rule.verdict(self.nodes, key)
+    }
+    fn next_index_verdict(&mut self) -> Verdict {
+        let verdict = self.verdict(ChildKey::Index(self.idx));
+        self.idx += 1;
+        verdict
+    }
+    fn wrap<'v, T: ?Sized>(
+        &self,
+        nodes: Vec<usize>,
+        value: &'v T,
+    ) -> Rewritten<'a, 'v, R, T> {
+        Rewritten {
+            rule: self.rule,
+            nodes,
+            value,
+        }
+    }
+    fn mask<'v, T: ?Sized>(
+        &self,
+        node: usize,
+        value: &'v T,
+    ) -> Masked<'a, 'v, R, T> {
+        Masked {
+            rule: self.rule,
+            node,
+            value,
+        }
+    }
+}
+impl<R: Rule, C: ser::SerializeSeq> ser::SerializeSeq for RewriteCompound<'_, R, C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+    fn serialize_element<T>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), C::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        match self.next_index_verdict() {
+            Verdict::Drop => Ok(()),
+            Verdict::Keep => self.inner.serialize_element(value),
+            Verdict::Enter(nodes) => self.inner.serialize_element(&self.wrap(nodes, value)),
+            Verdict::Mask(node) => self.inner.serialize_element(&self.mask(node, value)),
+        }
+    }
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+impl<R: Rule, C: ser::SerializeTuple> ser::SerializeTuple for RewriteCompound<'_, R, C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+    fn serialize_element<T>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), C::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        match self.next_index_verdict() {
+            Verdict::Drop => Ok(()),
+            Verdict::Keep => self.inner.serialize_element(value),
+            Verdict::Enter(nodes) => self.inner.serialize_element(&self.wrap(nodes, value)),
+            Verdict::Mask(node) => self.inner.serialize_element(&self.mask(node, value)),
+        }
+    }
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+impl<R: Rule, C: ser::SerializeTupleStruct> ser::SerializeTupleStruct
+    for RewriteCompound<'_, R, C>
+{
+    type Ok = C::Ok;
+    type Error = C::Error;
+    fn serialize_field<T>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), C::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        match self.next_index_verdict() {
+            Verdict::Drop => Ok(()),
+            Verdict::Keep => self.inner.serialize_field(value),
+            Verdict::Enter(nodes) => self.inner.serialize_field(&self.wrap(nodes, value)),
+            Verdict::Mask(node) => self.inner.serialize_field(&self.mask(node, value)),
+        }
+    }
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+impl<R: Rule, C: ser::SerializeTupleVariant> ser::SerializeTupleVariant
+    for RewriteCompound<'_, R, C>
+{
+    type Ok = C::Ok;
+    type Error = C::Error;
+    fn serialize_field<T>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), C::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        match self.next_index_verdict() {
+            Verdict::Drop => Ok(()),
+            Verdict::Keep => self.inner.serialize_field(value),
+            Verdict::Enter(nodes) => self.inner.serialize_field(&self.wrap(nodes, value)),
+            Verdict::Mask(node) => self.inner.serialize_field(&self.mask(node, value)),
+        }
+    }
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+impl<R: Rule, C: ser::SerializeMap> ser::SerializeMap for RewriteCompound<'_, R, C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+    fn serialize_key<T>(
+        &mut self,
+        key: &T,
+    ) -> Result<(), C::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.map_key.render(key).map_err(ser::Error::custom)?;
+        let verdict = self.verdict(ChildKey::Map(&self.map_key));
+        if !matches!(verdict, Verdict::Drop) {
+            self.inner.serialize_key(key)?;
+        }
+        self.next_map_value = Some(verdict);
+        Ok(())
+    }
+    fn serialize_value<T>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), C::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        match self.next_map_value.take() {
+            None | Some(Verdict::Drop) => Ok(()),
+            Some(Verdict::Keep) => self.inner.serialize_value(value),
+            Some(Verdict::Enter(nodes)) => self.inner.serialize_value(&self.wrap(nodes, value)),
+            Some(Verdict::Mask(node)) => self.inner.serialize_value(&self.mask(node, value)),
+        }
+    }
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+impl<R: Rule, C: ser::SerializeStruct> ser::SerializeStruct for RewriteCompound<'_, R, C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+    fn serialize_field<T>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), C::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        match self.verdict(ChildKey::Field(key)) {
+            Verdict::Drop => self.inner.skip_field(key),
+            Verdict::Keep => self.inner.serialize_field(key, value),
+            Verdict::Enter(nodes) => self.inner.serialize_field(key, &self.wrap(nodes, value)),
+            Verdict::Mask(node) => self.inner.serialize_field(key, &self.mask(node, value)),
+        }
+    }
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+impl<R: Rule, C: ser::SerializeStructVariant> ser::SerializeStructVariant
+    for RewriteCompound<'_, R, C>
+{
+    type Ok = C::Ok;
+    type Error = C::Error;
+    fn serialize_field<T>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), C::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        match self.verdict(ChildKey::Field(key)) {
+            Verdict::Drop => self.inner.skip_field(key),
+            Verdict::Keep => self.inner.serialize_field(key, value),
+            Verdict::Enter(nodes) => self.inner.serialize_field(key, &self.wrap(nodes, value)),
+            Verdict::Mask(node) => self.inner.serialize_field(key, &self.mask(node, value)),
+        }
+    }
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+/// "serialize" a value by counting its children which aren't dropped
+/// by the rule.
+///
+/// Like the `Sizer`, it doesn't go deeper than the first compound.
+struct Counter<'a, R> {
+    rule: &'a R,
+    nodes: &'a [usize],
+    idx: usize,
+    count: usize,
+    map_key: MapKey,
+    /// as the serializer of the rewritten value, as some types
+    /// (eg `IpAddr`) don't have the same structure in binary formats
+    human_readable: bool,
+}
+impl<R: Rule> Counter<'_, R> {
+    fn is_kept(
+        &self,
+        key: ChildKey<'_>,
+    ) -> bool {
+        !matches!(self.rule.verdict(self.nodes, key), Verdict::Drop)
+    }
+    fn count_key(
+        &mut self,
+        key: ChildKey<'_>,
+    ) {
+        if self.is_kept(key) {
+            self.count += 1;
+        }
+    }
+    fn count_next_index(&mut self) -> Result<(), IqInternalError> {
+        self.count_key(ChildKey::Index(self.idx));
+        self.idx += 1;
+        Ok(())
+    }
+    fn finish(&mut self) -> Result<(), IqInternalError> {
+        Err(IqInternalError::Count(self.count))
+    }
+    fn uncountable(&mut self) -> Result<(), IqInternalError> {
+        Err(IqInternalError::NoCount)
+    }
+}
+impl<R: Rule> ser::Serializer for &mut Counter<'_, R> {
+    type Ok = ();
+    type Error = IqInternalError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+    fn is_human_readable(&self) -> bool {
+        self.human_readable
+    }
+    fn serialize_bool(
+        self,
+        _v: bool,
+    ) -> Result<(), IqInternalError> {
+        self.uncountable()
+    }
+    fn serialize_i8(
+        self,
+        _v: i8,
+    ) -> Result<(), IqInternalError> {
+        self.uncountable()
+    }
+    fn serialize_i16(
+        self,
+        _v: i16,
+    ) -> Result<(), IqInternalError> {
+        self.uncountable()
+    }
+    fn serialize_i32(
+        self,
+        _v: i32,
+    ) -> Result<(), IqInternalError> {
+        self.uncountable()
+    }
+    fn serialize_i64(
+        self,
+        _v: i64,
+    ) -> Result<(), IqInternalError> {
+        self.uncountable()
+    }
+    fn serialize_u8(
+        self,
+        _v: u8,
+    ) -> Result<(), IqInternalError> {
+        self.uncountable()
+    }
+    fn serialize_u16(
+        self,
+        _v: u16,
+    ) -> Result<(), IqInternalError> {
+        self.uncountable()
+    }
+    fn serialize_u32(
+        self,
+        _v: u32,
+    ) -> Result<(), IqInternalError> {
+        self.uncountable()
+    }
+    fn serialize_u64(
+        self,
+        _v: u64,
+    ) -> Result<(), IqInternalError> {
+        self.uncountable()
+    }
+    fn serialize_f32(
+        self,
+        _v: f32,
+    ) -> Result<(), IqInternalError> {
+        self.uncountable()
+    }
+    fn serialize_f64(
+        self,
+        _v: f64,
+    ) -> Result<(), IqInternalError> {
+        self.uncountable()
+    }
+    fn serialize_char(
+        self,
+        _v: char,
+    ) -> Result<(), IqInternalError> {
+        self.uncountable()
+    }
+    fn serialize_str(
+        self,
+        _v: &str,
+    ) -> Result<(), IqInternalError> {
+        self.uncountable()
+    }
+    fn serialize_bytes(
+        self,
+        _v: &[u8],
+    ) -> Result<(), IqInternalError> {
+        self.uncountable()
+    }
+    fn serialize_none(self) -> Result<(), IqInternalError> {
+        self.uncountable()
+    }
+    fn serialize_some<T>(
+        self,
+        _value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        // the content will be counted when rewritten
+        self.uncountable()
+    }
+    fn serialize_unit(self) -> Result<(), IqInternalError> {
+        self.uncountable()
+    }
+    fn serialize_unit_struct(
+        self,
+        _name: &'static str,
+    ) -> Result<(), IqInternalError> {
+        self.uncountable()
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), IqInternalError> {
+        self.uncountable()
+    }
+    fn serialize_newtype_struct<T>(
+        self,
+        _name: &'static str,
+        _value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.uncountable()
+    }
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.uncountable()
+    }
+    fn serialize_seq(
+        self,
+        _len: Option<usize>,
+    ) -> Result<Self::SerializeSeq, IqInternalError> {
+        Ok(self)
+    }
+    fn serialize_tuple(
+        self,
+        _len: usize,
+    ) -> Result<Self::SerializeTuple, IqInternalError> {
+        Ok(self)
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, IqInternalError> {
+        Ok(self)
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, IqInternalError> {
+        Ok(self)
+    }
+    fn serialize_map(
+        self,
+        _len: Option<usize>,
+    ) -> Result<Self::SerializeMap, IqInternalError> {
+        Ok(self)
+    }
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, IqInternalError> {
+        Ok(self)
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, IqInternalError> {
+        Ok(self)
+    }
+}
+impl<R: Rule> ser::SerializeSeq for &mut Counter<'_, R> {
+    type Ok = ();
+    type Error = IqInternalError;
+    fn serialize_element<T>(
+        &mut self,
+        _value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.count_next_index()
+    }
+    fn end(self) -> Result<(), IqInternalError> {
+        self.finish()
+    }
+}
+impl<R: Rule> ser::SerializeTuple for &mut Counter<'_, R> {
+    type Ok = ();
+    type Error = IqInternalError;
+    fn serialize_element<T>(
+        &mut self,
+        _value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.count_next_index()
+    }
+    fn end(self) -> Result<(), IqInternalError> {
+        self.finish()
+    }
+}
+impl<R: Rule> ser::SerializeTupleStruct for &mut Counter<'_, R> {
+    type Ok = ();
+    type Error = IqInternalError;
+    fn serialize_field<T>(
+        &mut self,
+        _value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.count_next_index()
+    }
+    fn end(self) -> Result<(), IqInternalError> {
+        self.finish()
+    }
+}
+impl<R: Rule> ser::SerializeTupleVariant for &mut Counter<'_, R> {
+    type Ok = ();
+    type Error = IqInternalError;
+    fn serialize_field<T>(
+        &mut self,
+        _value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.count_next_index()
+    }
+    fn end(self) -> Result<(), IqInternalError> {
+        self.finish()
+    }
+}
+impl<R: Rule> ser::SerializeMap for &mut Counter<'_, R> {
+    type Ok = ();
+    type Error = IqInternalError;
+    fn serialize_key<T>(
+        &mut self,
+        key: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.map_key.render(key)?;
+        if self.is_kept(ChildKey::Map(&self.map_key)) {
+            self.count += 1;
+        }
+        Ok(())
+    }
+    fn serialize_value<T>(
+        &mut self,
+        _value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(())
+    }
+    fn end(self) -> Result<(), IqInternalError> {
+        self.finish()
+    }
+}
+impl<R: Rule> ser::SerializeStruct for &mut Counter<'_, R> {
+    type Ok = ();
+    type Error = IqInternalError;
+    fn serialize_field<T>(
+        &mut self,
+        key: &'static str,
+        _value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.count_key(ChildKey::Field(key));
+        Ok(())
+    }
+    fn end(self) -> Result<(), IqInternalError> {
+        self.finish()
+    }
+}
+impl<R: Rule> ser::SerializeStructVariant for &mut Counter<'_, R> {
+    type Ok = ();
+    type Error = IqInternalError;
+    fn serialize_field<T>(
+        &mut self,
+        key: &'static str,
+        _value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.count_key(ChildKey::Field(key));
+        Ok(())
+    }
+    fn end(self) -> Result<(), IqInternalError> {
+        self.finish()
+    }
+}
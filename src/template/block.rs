@@ -0,0 +1,257 @@
+use super::{
+    TemplateError,
+    Token,
+    placeholder::*,
+    tokenizer::Piece,
+};
+
+/// The meaning of the content of a pair of braces
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Tag {
+    Placeholder(Placeholder),
+    If(Placeholder),
+    Else,
+    EndIf,
+    Each(Vec<String>),
+    EndEach,
+}
+
+impl Tag {
+    /// Parse the content of a pair of braces, which is either a block
+    /// tag (`#if condition`, `#else`, `/if`, `#each path`, `/each`)
+    /// or a placeholder
+    fn parse(content: &str) -> Result<Self, String> {
+        let content = content.trim();
+        if let Some(tag) = content.strip_prefix('#') {
+            let (name, arg) = match tag.split_once(char::is_whitespace) {
+                Some((name, arg)) => (name, arg.trim()),
+                None => (tag, ""),
+            };
+            return match (name, arg) {
+                ("if", "") => Err("missing condition".to_string()),
+                ("if", condition) => {
+                    let condition = Placeholder::parse(condition)?;
+                    if condition.default.is_some() {
+                        return Err("a condition can't have a default value".to_string());
+                    }
+                    Ok(Self::If(condition))
+                }
+                ("each", "") => Err("missing path".to_string()),
+                ("each", path) => parse_path(path).map(Self::Each),
+                ("else", "") => Ok(Self::Else),
+                ("else", arg) => Err(format!("unexpected {:?} after `#else`", arg)),
+                _ => Err(format!("unknown block `{}`", name)),
+            };
+        }
+        match content {
+            "/if" => Ok(Self::EndIf),
+            "/each" => Ok(Self::EndEach),
+            _ => Placeholder::parse(content).map(Self::Placeholder),
+        }
+    }
+}
+
+/// A block whose end hasn't been met yet
+struct OpenBlock {
+    tag: Tag,
+    position: usize,
+    body: Vec<Token>,
+    otherwise: Option<Vec<Token>>,
+}
+
+impl OpenBlock {
+    fn new(
+        tag: Tag,
+        position: usize,
+    ) -> Self {
+        Self {
+            tag,
+            position,
+            body: Vec::new(),
+            otherwise: None,
+        }
+    }
+    fn push(
+        &mut self,
+        token: Token,
+    ) {
+        match &mut self.otherwise {
+            Some(otherwise) => otherwise.push(token),
+            None => self.body.push(token),
+        }
+    }
+    fn accepts(
+        &self,
+        tag: &Tag,
+    ) -> bool {
+        match (&self.tag, tag) {
+            (Tag::If(_), Tag::Else) => self.otherwise.is_none(),
+            (Tag::If(_), Tag::EndIf) => true,
+            (Tag::Each(_), Tag::EndEach) => true,
+            _ => false,
+        }
+    }
+    fn close(self) -> Token {
+        match self.tag {
+            Tag::Each(path) => Token::Each {
+                path,
+                body: self.body,
+            },
+            Tag::If(condition) => Token::If {
+                condition,
+                then: self.body,
+                otherwise: self.otherwise.unwrap_or_default(),
+            },
+            _ => Token::Literal(String::new()), // not expected
+        }
+    }
+}
+
+/// The tokens being built, with the stack of the open blocks
+#[derive(Default)]
+struct TreeBuilder {
+    root: Vec<Token>,
+    open: Vec<OpenBlock>,
+}
+
+impl TreeBuilder {
+    fn push(
+        &mut self,
+        token: Token,
+    ) {
+        match self.open.last_mut() {
+            Some(block) => block.push(token),
+            None => self.root.push(token),
+        }
+    }
+    /// Apply a tag, return false if it doesn't match the open blocks
+    fn apply(
+        &mut self,
+        tag: Tag,
+        position: usize,
+    ) -> bool {
+        match tag {
+            Tag::Placeholder(placeholder) => self.push(Token::Placeholder(placeholder)),
+            Tag::If(_) | Tag::Each(_) => self.open.push(OpenBlock::new(tag, position)),
+            Tag::Else | Tag::EndIf | Tag::EndEach => {
+                if !self.open.last().is_some_and(|block| block.accepts(&tag)) {
+                    return false;
+                }
+                if tag == Tag::Else {
+                    if let Some(block) = self.open.last_mut() {
+                        block.otherwise = Some(Vec::new());
+                    }
+                } else if let Some(block) = self.open.pop() {
+                    self.push(block.close());
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Build the tree of tokens from the pieces of a template.
+///
+/// When `strict`, invalid placeholders and block tags are errors.
+/// Otherwise they're kept as literal text, and unclosed blocks are
+/// closed at the end of the template.
+pub(crate) fn build_tokens(
+    pieces: Vec<Piece<'_>>,
+    strict: bool,
+) -> Result<Vec<Token>, TemplateError> {
+    let mut builder = TreeBuilder::default();
+    for piece in pieces {
+        let (content, position) = match piece {
+            Piece::Literal(literal) => {
+                builder.push(Token::Literal(literal));
+                continue;
+            }
+            Piece::Placeholder { content, position } => (content, position),
+        };
+        let error = match Tag::parse(content) {
+            Ok(tag) => {
+                if builder.apply(tag, position) {
+                    continue;
+                }
+                TemplateError::UnexpectedBlockTag {
+                    position,
+                    tag: content.trim().to_string(),
+                }
+            }
+            Err(reason) => TemplateError::InvalidPlaceholder { position, reason },
+        };
+        if strict {
+            return Err(error);
+        }
+        builder.push(Token::Literal(format!("{{{}}}", content)));
+    }
+    while let Some(block) = builder.open.pop() {
+        if strict {
+            return Err(TemplateError::UnclosedBlock {
+                position: block.position,
+            });
+        }
+        builder.push(block.close());
+    }
+    Ok(builder.root)
+}
+
+#[test]
+fn test_parse_tag() {
+    assert!(matches!(Tag::parse(" #if a.b "), Ok(Tag::If(_))));
+    assert!(matches!(Tag::parse("#if a | len"), Ok(Tag::If(_))));
+    assert_eq!(
+        Tag::parse("#each a.b"),
+        Ok(Tag::Each(vec!["a".to_string(), "b".to_string()]))
+    );
+    assert_eq!(Tag::parse("#each ."), Ok(Tag::Each(Vec::new())));
+    assert_eq!(Tag::parse("#else"), Ok(Tag::Else));
+    assert_eq!(Tag::parse("/if"), Ok(Tag::EndIf));
+    assert_eq!(Tag::parse("/each"), Ok(Tag::EndEach));
+    assert!(matches!(Tag::parse("a"), Ok(Tag::Placeholder(_))));
+    assert!(Tag::parse("#if").is_err());
+    assert!(Tag::parse("#if a ?? b").is_err());
+    assert!(Tag::parse("#each").is_err());
+    assert!(Tag::parse("#each a b").is_err());
+    assert!(Tag::parse("#else a").is_err());
+    assert!(Tag::parse("#while a").is_err());
+}
+
+#[test]
+fn test_build_tokens() {
+    use super::tokenizer::tokenize;
+    let build = |s| build_tokens(tokenize(s, true).unwrap(), true);
+    let tokens = build("a{#if b}c{#else}d{/if}{#each e}{f}{/each}").unwrap();
+    assert_eq!(tokens.len(), 3);
+    assert!(matches!(
+        &tokens[1],
+        Token::If { then, otherwise, .. } if then.len() == 1 && otherwise.len() == 1
+    ));
+    assert!(matches!(&tokens[2], Token::Each { body, .. } if body.len() == 1));
+    assert_eq!(
+        build("{#if a}{#each b}{/if}").unwrap_err(),
+        TemplateError::UnexpectedBlockTag {
+            position: 16,
+            tag: "/if".to_string(),
+        }
+    );
+    assert_eq!(
+        build("{#if a}{#else}{#else}{/if}").unwrap_err(),
+        TemplateError::UnexpectedBlockTag {
+            position: 14,
+            tag: "#else".to_string(),
+        }
+    );
+    assert_eq!(
+        build("x{#each a}{#if b}{/if}").unwrap_err(),
+        TemplateError::UnclosedBlock { position: 1 }
+    );
+    assert!(matches!(
+        build("{#loop a}"),
+        Err(TemplateError::InvalidPlaceholder { position: 0, .. })
+    ));
+    // lenient: stray tags are literal, unclosed blocks closed at the end
+    let lenient = |s| build_tokens(tokenize(s, false).unwrap(), false).unwrap();
+    assert!(matches!(&lenient("{/each}")[..], [Token::Literal(lit)] if lit == "{/each}"));
+    assert!(matches!(&lenient("{#each a}b")[..], [Token::Each { .. }]));
+}
@@ -0,0 +1,515 @@
+use {
+    super::{
+        filter::*,
+        placeholder::Placeholder,
+        *,
+    },
+    serde_json::Value,
+    std::{
+        collections::HashMap,
+        fmt,
+        ops::ControlFlow,
+    },
+};
+
+/// Renders templates, with custom filters in addition to the
+/// built-in ones.
+///
+/// ```
+/// use iq::*;
+/// let engine = TemplateEngine::new()
+///     .with_filter("double", |value, _args| {
+///         let n = value.as_i64().ok_or("not an integer")?;
+///         Ok((n * 2).into())
+///     });
+/// let template = Template::new("{0 | double} {1 | upper}");
+/// assert_eq!(engine.render(&template, (21, "ok")), "42 OK");
+/// ```
+#[derive(Default)]
+pub struct TemplateEngine {
+    filters: HashMap<String, Box<Filter>>,
+}
+
+impl fmt::Debug for TemplateEngine {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        f.debug_struct("TemplateEngine")
+            .field("filters", &self.filters.keys())
+            .finish()
+    }
+}
+
+impl TemplateEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Register a filter, replacing any filter with the same name,
+    /// built-in ones included.
+    ///
+    /// The filter receives the value (null when there's none) and the
+    /// arguments of the call, and returns the new value or the reason
+    /// of its failure.
+    pub fn with_filter<F>(
+        mut self,
+        name: &str,
+        filter: F,
+    ) -> Self
+    where
+        F: Fn(&Value, &[Value]) -> Result<Value, String> + Send + Sync + 'static,
+    {
+        self.filters.insert(name.to_string(), Box::new(filter));
+        self
+    }
+    /// Render the template with the values found in data, rendering
+    /// missing values with the defaults or the missing marker
+    pub fn render<T>(
+        &self,
+        template: &Template,
+        data: T,
+    ) -> String
+    where
+        T: Serialize,
+    {
+        self.render_checked(template, &data, false).0
+    }
+    /// Render the template, failing with the list of all the placeholders
+    /// which can't be rendered and have no default value
+    pub fn render_strict<T>(
+        &self,
+        template: &Template,
+        data: T,
+    ) -> Result<String, TemplateError>
+    where
+        T: Serialize,
+    {
+        let (rendered, unresolved) = self.render_checked(template, &data, true);
+        if unresolved.is_empty() {
+            Ok(rendered)
+        } else {
+            Err(TemplateError::Unresolved(unresolved))
+        }
+    }
+    /// Render the template, returning also the placeholders which
+    /// couldn't be rendered and have no default value.
+    ///
+    /// The reasons are computed only when `strict`.
+    fn render_checked<T: Serialize>(
+        &self,
+        template: &Template,
+        data: &T,
+        strict: bool,
+    ) -> (String, Vec<UnresolvedPlaceholder>) {
+        let mut rendering = Rendering {
+            engine: self,
+            missing_marker: template.missing_marker.as_deref(),
+            strict,
+            output: String::new(),
+            unresolved: Vec::new(),
+        };
+        rendering.render(&template.tokens, data, &Scope::default());
+        (rendering.output, rendering.unresolved)
+    }
+    fn apply(
+        &self,
+        call: &FilterCall,
+        value: &Value,
+    ) -> Result<Value, UnresolvedReason> {
+        let result = match self.filters.get(&call.name) {
+            Some(filter) => filter(value, &call.args),
+            None => match builtin(&call.name) {
+                Some(filter) => filter(value, &call.args),
+                None => return Err(UnresolvedReason::UnknownFilter(call.name.clone())),
+            },
+        };
+        result.map_err(|message| UnresolvedReason::FilterFailed {
+            filter: call.name.clone(),
+            message,
+        })
+    }
+}
+
+/// Where the rendering is: the concrete path of the current data,
+/// and the index and count of the items of the innermost loop
+#[derive(Default)]
+struct Scope {
+    path: Vec<String>,
+    looping: Option<(usize, usize)>,
+}
+
+impl Scope {
+    /// The path from the root of the data
+    fn absolute(
+        &self,
+        path: &[String],
+    ) -> Vec<String> {
+        self.path.iter().chain(path).cloned().collect()
+    }
+    /// The value of a loop variable, eg `@index`
+    fn variable(
+        &self,
+        name: &str,
+    ) -> Option<Value> {
+        let (index, count) = self.looping?;
+        match name {
+            "@index" => Some(index.into()),
+            "@first" => Some((index == 0).into()),
+            "@last" => Some((index + 1 == count).into()),
+            "@key" => self.path.last().map(|key| key.as_str().into()),
+            _ => None,
+        }
+    }
+}
+
+/// The name of the loop variable designated by a path, if any
+fn variable(path: &[String]) -> Option<&str> {
+    match path {
+        [name] if name.starts_with('@') => Some(name),
+        _ => None,
+    }
+}
+
+/// Tell whether a condition is met by a value
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+    }
+}
+
+/// The state of the rendering of a template
+struct Rendering<'r> {
+    engine: &'r TemplateEngine,
+    missing_marker: Option<&'r str>,
+    strict: bool,
+    output: String,
+    unresolved: Vec<UnresolvedPlaceholder>,
+}
+
+impl Rendering<'_> {
+    fn render<T: Serialize>(
+        &mut self,
+        tokens: &[Token],
+        data: &T,
+        scope: &Scope,
+    ) {
+        let paths: Vec<&Vec<String>> = tokens
+            .iter()
+            .filter_map(|token| match token {
+                Token::Placeholder(placeholder) => Some(&placeholder.path),
+                _ => None,
+            })
+            .collect();
+        // on a serialization error, the paths are resolved one by one so
+        // that the other placeholders are still rendered, and the error
+        // is attributed to the right placeholders
+        let values = extract_many_checked(data, &paths, IqFormat::Primitive).unwrap_or_else(|_| {
+            paths
+                .iter()
+                .map(|path| resolve_primitive(data, path).ok())
+                .collect()
+        });
+        let mut values = values.into_iter();
+        for token in tokens {
+            match token {
+                Token::Literal(lit) => self.output.push_str(lit),
+                Token::Placeholder(placeholder) => {
+                    let primitive = values.next().flatten();
+                    self.render_placeholder(data, placeholder, primitive, scope);
+                }
+                Token::If {
+                    condition,
+                    then,
+                    otherwise,
+                } => {
+                    let branch = if self.check(data, condition, scope) {
+                        then
+                    } else {
+                        otherwise
+                    };
+                    self.render(branch, data, scope);
+                }
+                Token::Each { path, body } => self.render_each(data, path, body, scope),
+            }
+        }
+    }
+    fn render_placeholder<T: Serialize>(
+        &mut self,
+        data: &T,
+        placeholder: &Placeholder,
+        primitive: Option<String>,
+        scope: &Scope,
+    ) {
+        let reason = match self.resolve(data, placeholder, primitive, scope) {
+            Ok(s) => {
+                self.output.push_str(&s);
+                return;
+            }
+            Err(reason) => reason,
+        };
+        match &placeholder.default {
+            Some(default) => self.output.push_str(default),
+            None => {
+                if let Some(marker) = self.missing_marker {
+                    self.output.push_str(marker);
+                }
+                self.unresolved.push(UnresolvedPlaceholder {
+                    path: scope.absolute(&placeholder.path),
+                    reason,
+                });
+            }
+        }
+    }
+    /// Render the value of a placeholder, given the primitive found at
+    /// its path, or tell why it's not possible
+    fn resolve<T: Serialize>(
+        &self,
+        data: &T,
+        placeholder: &Placeholder,
+        primitive: Option<String>,
+        scope: &Scope,
+    ) -> Result<String, UnresolvedReason> {
+        if placeholder.filters.is_empty() && variable(&placeholder.path).is_none() {
+            return match primitive {
+                Some(s) => Ok(s),
+                None if self.strict => resolve_primitive(data, &placeholder.path),
+                None => Err(UnresolvedReason::NotFound),
+            };
+        }
+        match self.value(data, placeholder, scope)? {
+            Value::Null => Err(UnresolvedReason::NotFound),
+            value => primitive_text(&value).ok_or(UnresolvedReason::NotPrimitive),
+        }
+    }
+    /// The value at the path of the placeholder, or of its loop
+    /// variable, transformed by its filters. Null if there's none.
+    fn value<T: Serialize>(
+        &self,
+        data: &T,
+        placeholder: &Placeholder,
+        scope: &Scope,
+    ) -> Result<Value, UnresolvedReason> {
+        let mut value = match variable(&placeholder.path) {
+            Some(name) => scope.variable(name).unwrap_or(Value::Null),
+            None => extract_value::<_, _, Value>(data, &placeholder.path)
+                .map_err(|e| UnresolvedReason::Serialization(e.to_string()))?
+                .unwrap_or(Value::Null),
+        };
+        for call in &placeholder.filters {
+            value = self.engine.apply(call, &value)?;
+        }
+        Ok(value)
+    }
+    /// Evaluate the condition of an `#if` block
+    fn check<T: Serialize>(
+        &mut self,
+        data: &T,
+        condition: &Placeholder,
+        scope: &Scope,
+    ) -> bool {
+        match self.value(data, condition, scope) {
+            Ok(value) => is_truthy(&value),
+            Err(reason) => {
+                self.unresolved.push(UnresolvedPlaceholder {
+                    path: scope.absolute(&condition.path),
+                    reason,
+                });
+                false
+            }
+        }
+    }
+    /// Render the body of an `#each` block for every item at the path
+    fn render_each<T: Serialize>(
+        &mut self,
+        data: &T,
+        path: &[String],
+        body: &[Token],
+        scope: &Scope,
+    ) {
+        let count = match extract_size(data, path) {
+            Some(count) => count,
+            None => {
+                self.unresolved.push(UnresolvedPlaceholder {
+                    path: scope.absolute(path),
+                    reason: UnresolvedReason::NotFound,
+                });
+                return;
+            }
+        };
+        let mut pattern = path.to_vec();
+        pattern.push("*".to_string());
+        let mut index = 0;
+        let result = for_each(data, &pattern, |keys, node| {
+            let item_scope = Scope {
+                path: scope.absolute(keys),
+                looping: Some((index, count)),
+            };
+            self.render(body, &node, &item_scope);
+            index += 1;
+            ControlFlow::Continue(())
+        });
+        if let Err(e) = result {
+            self.unresolved.push(UnresolvedPlaceholder {
+                path: scope.absolute(path),
+                reason: UnresolvedReason::Serialization(e.to_string()),
+            });
+        }
+    }
+}
+
+/// Render the primitive at the path or tell why it's not possible
+fn resolve_primitive<T: Serialize>(
+    data: &T,
+    path: &[String],
+) -> Result<String, UnresolvedReason> {
+    match extract_string_checked(data, path, IqFormat::Primitive) {
+        Ok(Some(s)) => Ok(s),
+        Ok(None) => match extract_size(data, path) {
+            Some(_) => Err(UnresolvedReason::NotPrimitive),
+            None => Err(UnresolvedReason::NotFound),
+        },
+        Err(e) => Err(UnresolvedReason::Serialization(e.to_string())),
+    }
+}
+
+#[test]
+fn test_engine() {
+    #[derive(Serialize)]
+    struct Car {
+        driver: &'static str,
+        price: f64,
+        tags: Vec<&'static str>,
+        passengers: Vec<&'static str>,
+        created: Option<&'static str>,
+        desc: &'static str,
+    }
+    let car = Car {
+        driver: "Rex",
+        price: 12499.999,
+        tags: vec!["red", "fast"],
+        passengers: vec!["Alice", "Laïka"],
+        created: None,
+        desc: "A very fast and very red car",
+    };
+    let template = Template::parse(
+        r#"{driver | upper} {price | round:2} {tags | join:", "} {passengers | len} {created | default:"n/a"} {desc | truncate:11,"…"}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        template.render(&car),
+        "REX 12500.00 red, fast 2 n/a A very fast…"
+    );
+    let engine = TemplateEngine::new()
+        .with_filter("first", |value, _| {
+            Ok(value.get(0).cloned().unwrap_or(Value::Null))
+        })
+        .with_filter("upper", |_, _| Ok("shadowed".into()));
+    let template = Template::new("{tags | first | lower} {driver | upper}");
+    assert_eq!(engine.render(&template, &car), "red shadowed");
+    // filter errors, in lenient and strict renderings
+    let template = Template::new(r#"{driver | round} {tags | nope ?? "-"} {driver | nope}"#)
+        .with_missing_marker("?");
+    assert_eq!(template.render(&car), "? - ?");
+    let err = template.render_strict(&car).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unresolved placeholders: {driver} (filter `round` failed: not a number), {driver} (unknown filter `nope`)"
+    );
+    let err = Template::new("{tags | lower}")
+        .render_strict(&car)
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unresolved placeholders: {tags} (filter `lower` failed: not a primitive)"
+    );
+    let err = Template::new("{tags | join:1,2}")
+        .render_strict(&car)
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unresolved placeholders: {tags} (filter `join` failed: 0 to 1 arguments expected)"
+    );
+}
+
+#[test]
+fn test_blocks() {
+    #[derive(Serialize)]
+    struct Passenger {
+        name: &'static str,
+        ears: u8,
+        tags: Vec<&'static str>,
+    }
+    #[derive(Serialize)]
+    struct Diagnostic {
+        disease: Option<&'static str>,
+    }
+    #[derive(Serialize)]
+    struct Alert {
+        diag: Diagnostic,
+        passengers: Vec<Passenger>,
+        driver: &'static str,
+    }
+    let mut alert = Alert {
+        diag: Diagnostic {
+            disease: Some("flu"),
+        },
+        passengers: vec![
+            Passenger {
+                name: "Alice",
+                ears: 2,
+                tags: vec!["vip"],
+            },
+            Passenger {
+                name: "Laïka",
+                ears: 2,
+                tags: vec![],
+            },
+        ],
+        driver: "Rex",
+    };
+    let template = Template::parse(
+        "{#if diag.disease}sick: {diag.disease | upper}{#else}healthy{/if}. {#each passengers}{name} ({ears}){#if @last}.{#else}, {/if}{/each}",
+    )
+    .unwrap();
+    assert_eq!(template.render(&alert), "sick: FLU. Alice (2), Laïka (2).");
+    let template = Template::parse(
+        "{#each passengers}{@index}:{#each tags}{.}{/each}{#if tags}!{/if}{#if @first} {/if}{/each}",
+    )
+    .unwrap();
+    assert_eq!(template.render(&alert), "0:vip! 1:");
+    let template = Template::parse("{#each passengers.0}{@key}={.};{/each}").unwrap();
+    assert_eq!(template.render(&alert), "name=Alice;ears=2;tags=;",);
+    let template =
+        Template::parse("{#if passengers | len}{passengers | len} passengers{/if}").unwrap();
+    assert_eq!(template.render(&alert), "2 passengers");
+    alert.diag.disease = None;
+    alert.passengers.clear();
+    let template =
+        Template::parse("{#if diag.disease}sick{#else}healthy{/if}{#each passengers}{name}{/each}")
+            .unwrap();
+    assert_eq!(template.render(&alert), "healthy");
+    // strict rendering, with the paths of the unresolved placeholders
+    // given from the root
+    let alert = Alert {
+        diag: Diagnostic { disease: None },
+        passengers: vec![Passenger {
+            name: "Alice",
+            ears: 2,
+            tags: vec![],
+        }],
+        driver: "Rex",
+    };
+    let template = Template::parse(
+        "{#each passengers}{name} {nose} {driver}{/each}{#each crew}{name}{/each}{@index}",
+    )
+    .unwrap();
+    assert_eq!(
+        template.render_strict(&alert).unwrap_err().to_string(),
+        "unresolved placeholders: {passengers.0.nose} (not found), {passengers.0.driver} (not found), {crew} (not found), {@index} (not found)",
+    );
+}
@@ -0,0 +1,118 @@
+use std::fmt;
+
+/// Error of a template, with the position (in bytes) of the
+/// problematic brace in the template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    /// A `{` isn't closed by a `}`
+    UnclosedBrace { position: usize },
+    /// A `}` doesn't close a placeholder
+    UnexpectedClosingBrace { position: usize },
+    /// A placeholder is blank, eg `{}`
+    EmptyPlaceholder { position: usize },
+    /// The content of a placeholder can't be interpreted, eg an
+    /// unclosed string in its default value
+    InvalidPlaceholder { position: usize, reason: String },
+    /// A `{#if}` or `{#each}` block isn't closed
+    UnclosedBlock { position: usize },
+    /// A block tag (eg `{/if}` or `{#else}`) doesn't match an open block
+    UnexpectedBlockTag { position: usize, tag: String },
+    /// Some placeholders couldn't be rendered by a strict rendering
+    Unresolved(Vec<UnresolvedPlaceholder>),
+}
+
+/// A placeholder which couldn't be rendered, and why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnresolvedPlaceholder {
+    /// The iq path of the placeholder, from the root of the data (eg
+    /// `passengers.1.name` for a `{name}` in a loop on `passengers`)
+    pub path: Vec<String>,
+    pub reason: UnresolvedReason,
+}
+
+/// Why a placeholder couldn't be rendered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnresolvedReason {
+    /// There's no value at the path
+    NotFound,
+    /// The value at the path is a map, a struct, a sequence, etc.
+    NotPrimitive,
+    /// The data couldn't be serialized
+    Serialization(String),
+    /// A filter of the placeholder isn't known
+    UnknownFilter(String),
+    /// A filter of the placeholder failed, eg `round` on a string
+    FilterFailed { filter: String, message: String },
+}
+
+impl std::error::Error for TemplateError {}
+
+impl fmt::Display for TemplateError {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        match self {
+            Self::UnclosedBrace { position } => {
+                write!(f, "unclosed `{{` at position {}", position)
+            }
+            Self::UnexpectedClosingBrace { position } => write!(
+                f,
+                "unexpected `}}` at position {} (use `}}}}` for a literal one)",
+                position
+            ),
+            Self::EmptyPlaceholder { position } => {
+                write!(f, "empty placeholder at position {}", position)
+            }
+            Self::InvalidPlaceholder { position, reason } => {
+                write!(
+                    f,
+                    "invalid placeholder at position {}: {}",
+                    position, reason
+                )
+            }
+            Self::UnclosedBlock { position } => {
+                write!(f, "unclosed block at position {}", position)
+            }
+            Self::UnexpectedBlockTag { position, tag } => {
+                write!(f, "unexpected `{{{}}}` at position {}", tag, position)
+            }
+            Self::Unresolved(placeholders) => {
+                write!(f, "unresolved placeholders: ")?;
+                for (i, placeholder) in placeholders.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    placeholder.fmt(f)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for UnresolvedPlaceholder {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        write!(f, "{{{}}} ({})", self.path.join("."), self.reason)
+    }
+}
+
+impl fmt::Display for UnresolvedReason {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        match self {
+            Self::NotFound => write!(f, "not found"),
+            Self::NotPrimitive => write!(f, "not a primitive"),
+            Self::Serialization(msg) => write!(f, "serialization error: {}", msg),
+            Self::UnknownFilter(name) => write!(f, "unknown filter `{}`", name),
+            Self::FilterFailed { filter, message } => {
+                write!(f, "filter `{}` failed: {}", filter, message)
+            }
+        }
+    }
+}
@@ -0,0 +1,385 @@
+use {
+    super::tokenizer::*,
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    serde_json::Value,
+};
+
+/// A function transforming the value of a placeholder, given the
+/// arguments of the filter call
+pub(crate) type Filter = dyn Fn(&Value, &[Value]) -> Result<Value, String> + Send + Sync;
+
+type BuiltinFilter = fn(&Value, &[Value]) -> Result<Value, String>;
+
+/// A filter applied to the value of a placeholder, eg `round:2`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct FilterCall {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<Value>,
+}
+
+impl FilterCall {
+    /// Parse a filter call: a name, optionally followed by a `:` and
+    /// comma separated arguments, eg `truncate:40,"…"`.
+    ///
+    /// An argument is either a double-quoted string, a JSON number,
+    /// boolean or null, or some bare text.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let (name, args) = match text.split_once(':') {
+            Some((name, args)) => (name.trim(), Some(args)),
+            None => (text.trim(), None),
+        };
+        if name.is_empty() {
+            return Err("missing filter name".to_string());
+        }
+        if !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(format!("invalid filter name {:?}", name));
+        }
+        let args = match args {
+            Some(args) => split_unquoted(args, ",")
+                .into_iter()
+                .map(parse_arg)
+                .collect::<Result<_, _>>()?,
+            None => Vec::new(),
+        };
+        Ok(Self {
+            name: name.to_string(),
+            args,
+        })
+    }
+}
+
+fn parse_arg(text: &str) -> Result<Value, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("empty filter argument".to_string());
+    }
+    if text.starts_with('"') {
+        return unquote(text).map(Value::String);
+    }
+    match serde_json::from_str(text) {
+        Ok(value @ (Value::Number(_) | Value::Bool(_) | Value::Null)) => Ok(value),
+        _ => Ok(Value::String(text.to_string())),
+    }
+}
+
+/// The text rendering a primitive value, None for null, arrays and maps
+pub(crate) fn primitive_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Get a built-in filter by name
+pub(crate) fn builtin(name: &str) -> Option<BuiltinFilter> {
+    Some(match name {
+        "upper" => upper,
+        "lower" => lower,
+        "trim" => trim,
+        "round" => round,
+        "join" => join,
+        "len" => len,
+        "default" => default,
+        "truncate" => truncate,
+        _ => return None,
+    })
+}
+
+fn check_arg_count(
+    args: &[Value],
+    min: usize,
+    max: usize,
+) -> Result<(), String> {
+    if args.len() < min || args.len() > max {
+        return Err(match (min, max) {
+            (0, 0) => "no argument expected".to_string(),
+            (min, max) if min == max => format!("{} argument(s) expected", min),
+            (min, max) => format!("{} to {} arguments expected", min, max),
+        });
+    }
+    Ok(())
+}
+
+fn usize_arg(
+    args: &[Value],
+    idx: usize,
+) -> Result<Option<usize>, String> {
+    match args.get(idx) {
+        None => Ok(None),
+        Some(arg) => arg
+            .as_u64()
+            .map(|n| Some(n as usize))
+            .ok_or_else(|| format!("argument {} isn't a positive integer", arg)),
+    }
+}
+
+fn text_arg(
+    args: &[Value],
+    idx: usize,
+) -> Result<Option<String>, String> {
+    match args.get(idx) {
+        None => Ok(None),
+        Some(arg) => primitive_text(arg)
+            .map(Some)
+            .ok_or_else(|| format!("argument {} isn't a primitive", arg)),
+    }
+}
+
+/// Apply a string transformation to a primitive value, null being kept
+fn map_text<F: Fn(&str) -> String>(
+    value: &Value,
+    f: F,
+) -> Result<Value, String> {
+    if value.is_null() {
+        return Ok(Value::Null);
+    }
+    let text = primitive_text(value).ok_or("not a primitive")?;
+    Ok(Value::String(f(&text)))
+}
+
+fn upper(
+    value: &Value,
+    args: &[Value],
+) -> Result<Value, String> {
+    check_arg_count(args, 0, 0)?;
+    map_text(value, str::to_uppercase)
+}
+
+fn lower(
+    value: &Value,
+    args: &[Value],
+) -> Result<Value, String> {
+    check_arg_count(args, 0, 0)?;
+    map_text(value, str::to_lowercase)
+}
+
+fn trim(
+    value: &Value,
+    args: &[Value],
+) -> Result<Value, String> {
+    check_arg_count(args, 0, 0)?;
+    map_text(value, |s| s.trim().to_string())
+}
+
+/// The max number of decimals of `round`, beyond which an f64
+/// has no significant digit
+const MAX_ROUND_DECIMALS: usize = 17;
+
+/// Round a number to some decimals (0 by default), rendering all of them.
+///
+/// The rounding (half away from zero) is done on the shortest decimal
+/// representation of the number, the one written in the data, so that
+/// `1.005` is rounded to `1.01` even if its closest f64 is a little less.
+fn round(
+    value: &Value,
+    args: &[Value],
+) -> Result<Value, String> {
+    check_arg_count(args, 0, 1)?;
+    let decimals = usize_arg(args, 0)?.unwrap_or(0);
+    if decimals > MAX_ROUND_DECIMALS {
+        return Err(format!("at most {} decimals expected", MAX_ROUND_DECIMALS));
+    }
+    let number = match value {
+        Value::Null => return Ok(Value::Null),
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    };
+    let number = number.ok_or("not a number")?;
+    Ok(Value::String(round_decimal(number, decimals)))
+}
+
+/// Render a number with the given number of decimals, rounding half
+/// away from zero its shortest decimal representation
+fn round_decimal(
+    number: f64,
+    decimals: usize,
+) -> String {
+    if !number.is_finite() {
+        return number.to_string();
+    }
+    // eg "1.005e0": the digits, and the position of the decimal point
+    let sci = format!("{:e}", number.abs());
+    let (mantissa, exp) = sci.split_once('e').unwrap_or((&sci, "0"));
+    let mut digits: Vec<u8> = mantissa
+        .bytes()
+        .filter(u8::is_ascii_digit)
+        .map(|b| b - b'0')
+        .collect();
+    let mut point = exp.parse::<i64>().unwrap_or(0) + 1;
+    let keep = point + decimals as i64;
+    if keep < 0 {
+        digits.clear();
+    } else {
+        let round_up = digits.get(keep as usize).is_some_and(|&d| d >= 5);
+        digits.resize(keep as usize, 0);
+        if round_up {
+            // add one to the last kept digit, with the carry
+            match digits.iter().rposition(|&d| d < 9) {
+                Some(idx) => {
+                    digits[idx] += 1;
+                    digits[idx + 1..].iter_mut().for_each(|d| *d = 0);
+                }
+                None => {
+                    digits.iter_mut().for_each(|d| *d = 0);
+                    digits.insert(0, 1);
+                    point += 1;
+                }
+            }
+        }
+    }
+    // left pad with zeros so that there's at least one digit before the point
+    let padding = (1 - point).max(0) as usize;
+    let point = point.max(1) as usize;
+    let digits = std::iter::repeat(0)
+        .take(padding)
+        .chain(digits)
+        .chain(std::iter::repeat(0))
+        .take(point + decimals);
+    let mut text = String::new();
+    for (idx, digit) in digits.enumerate() {
+        if idx == point {
+            text.push('.');
+        }
+        text.push((b'0' + digit) as char);
+    }
+    if number < 0.0 && text.bytes().any(|b| (b'1'..=b'9').contains(&b)) {
+        text.insert(0, '-');
+    }
+    text
+}
+
+/// Join the items of an array, with `", "` as default separator
+fn join(
+    value: &Value,
+    args: &[Value],
+) -> Result<Value, String> {
+    check_arg_count(args, 0, 1)?;
+    let separator = text_arg(args, 0)?.unwrap_or_else(|| ", ".to_string());
+    let items = match value {
+        Value::Null => return Ok(Value::Null),
+        Value::Array(items) => items,
+        _ => return Err("not an array".to_string()),
+    };
+    let texts: Vec<String> = items
+        .iter()
+        .map(|item| primitive_text(item).unwrap_or_else(|| item.to_string()))
+        .collect();
+    Ok(Value::String(texts.join(&separator)))
+}
+
+/// The number of items of an array or map, or of chars of a string
+fn len(
+    value: &Value,
+    args: &[Value],
+) -> Result<Value, String> {
+    check_arg_count(args, 0, 0)?;
+    let len = match value {
+        Value::Null => return Ok(Value::Null),
+        Value::Array(items) => items.len(),
+        Value::Object(map) => map.len(),
+        Value::String(s) => s.chars().count(),
+        _ => return Err("not an array, a map or a string".to_string()),
+    };
+    Ok(Value::from(len))
+}
+
+/// Replace a null or empty string value
+fn default(
+    value: &Value,
+    args: &[Value],
+) -> Result<Value, String> {
+    check_arg_count(args, 1, 1)?;
+    match value {
+        Value::Null => Ok(args[0].clone()),
+        Value::String(s) if s.is_empty() => Ok(args[0].clone()),
+        _ => Ok(value.clone()),
+    }
+}
+
+/// Keep at most some chars of a string, appending the optional
+/// second argument when it's truncated
+fn truncate(
+    value: &Value,
+    args: &[Value],
+) -> Result<Value, String> {
+    check_arg_count(args, 1, 2)?;
+    let max = usize_arg(args, 0)?.unwrap_or(0);
+    let ellipsis = text_arg(args, 1)?.unwrap_or_default();
+    map_text(value, |s| match s.char_indices().nth(max) {
+        Some((idx, _)) => format!("{}{}", &s[..idx], ellipsis),
+        None => s.to_string(),
+    })
+}
+
+#[test]
+fn test_filters() {
+    use serde_json::json;
+    let apply = |call: &str, value: Value| {
+        let call = FilterCall::parse(call)?;
+        let filter = builtin(&call.name).ok_or("unknown")?;
+        filter(&value, &call.args)
+    };
+    assert_eq!(apply("upper", json!("Rex")), Ok(json!("REX")));
+    assert_eq!(apply("lower", json!(true)), Ok(json!("true")));
+    assert_eq!(apply("upper", Value::Null), Ok(Value::Null));
+    assert!(apply("upper", json!([1])).is_err());
+    assert!(apply("upper:2", json!("a")).is_err());
+    assert_eq!(apply("trim", json!(" a ")), Ok(json!("a")));
+    assert_eq!(apply("round:2", json!(12.3456)), Ok(json!("12.35")));
+    assert_eq!(apply("round:2", json!(2.5)), Ok(json!("2.50")));
+    assert_eq!(apply("round", json!("2.5")), Ok(json!("3")));
+    assert!(apply("round:a", json!(2.5)).is_err());
+    assert_eq!(
+        apply("round:17", json!(0.5)),
+        Ok(json!("0.50000000000000000"))
+    );
+    assert!(apply("round:18", json!(2.5)).is_err());
+    // rounded as written, not as the closest f64
+    assert_eq!(apply("round:2", json!(1.005)), Ok(json!("1.01")));
+    assert_eq!(apply("round:1", json!(-0.25)), Ok(json!("-0.3")));
+    assert_eq!(apply("round:1", json!(-0.04)), Ok(json!("0.0")));
+    assert_eq!(apply("round", json!(9.5)), Ok(json!("10")));
+    assert_eq!(apply("round:2", json!(0.0001)), Ok(json!("0.00")));
+    assert_eq!(apply("round:3", json!(0.0006)), Ok(json!("0.001")));
+    assert_eq!(apply("round:1", json!(123)), Ok(json!("123.0")));
+    // large numbers don't overflow
+    let big = apply("round:17", json!(1e300)).unwrap();
+    assert_eq!(
+        big,
+        json!(format!("1{}.{}", "0".repeat(300), "0".repeat(17)))
+    );
+    assert!(apply("round:4000000000", json!(2.5)).is_err());
+    assert!(apply("round", json!("a")).is_err());
+    assert_eq!(apply(r#"join:", ""#, json!(["a", 1])), Ok(json!("a, 1")));
+    assert_eq!(apply("join: - ", json!(["a", "b"])), Ok(json!("a-b")));
+    assert_eq!(
+        apply(r#"join:" - ""#, json!(["a", "b"])),
+        Ok(json!("a - b"))
+    );
+    assert!(apply("join", json!("a")).is_err());
+    assert_eq!(apply("len", json!([1, 2])), Ok(json!(2)));
+    assert_eq!(apply("len", json!("Laïka")), Ok(json!(5)));
+    assert_eq!(apply(r#"default:"n/a""#, Value::Null), Ok(json!("n/a")));
+    assert_eq!(apply("default:0", json!("")), Ok(json!(0)));
+    assert_eq!(apply("default:0", json!(3)), Ok(json!(3)));
+    assert!(apply("default", json!(3)).is_err());
+    assert_eq!(apply("truncate:3", json!("Laïka")), Ok(json!("Laï")));
+    assert_eq!(
+        apply(r#"truncate:3,"…""#, json!("Laïka")),
+        Ok(json!("Laï…"))
+    );
+    assert_eq!(
+        apply(r#"truncate:5,"…""#, json!("Laïka")),
+        Ok(json!("Laïka"))
+    );
+    assert!(FilterCall::parse("up per").is_err());
+    assert!(FilterCall::parse("round:").is_err());
+    assert!(FilterCall::parse(r#"join:"a"#).is_err());
+}
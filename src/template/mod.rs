@@ -0,0 +1,297 @@
+mod block;
+mod engine;
+mod error;
+mod filter;
+mod placeholder;
+mod tokenizer;
+
+use {
+    crate::*,
+    block::build_tokens,
+    placeholder::Placeholder,
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    std::str::FromStr,
+    tokenizer::*,
+};
+pub use {
+    engine::TemplateEngine,
+    error::*,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum Token {
+    Literal(String),
+    #[serde(alias = "IqPath")] // the name in iq 0.4
+    Placeholder(Placeholder),
+    If {
+        condition: Placeholder,
+        then: Vec<Token>,
+        otherwise: Vec<Token>,
+    },
+    Each {
+        path: Vec<String>,
+        body: Vec<Token>,
+    },
+}
+
+/// A template that can be rendered with data.
+///
+/// Placeholders are iq paths between braces. Use `{{` and `}}` for
+/// literal braces. Braces whose content can't be a path, like some code
+/// or JSON (`{ return 1; }`, `{"a": 1}`), are kept as literal text by
+/// [`Template::new`].
+///
+/// A placeholder may give a default value, rendered when there's no
+/// primitive at the path, either as `{path ?? "default"}` or as
+/// `{path:-default}`. Other missing values are rendered as nothing,
+/// or as the marker given with [`Template::with_missing_marker`].
+///
+/// The value of a placeholder can be transformed by filters, eg
+/// `{driver.name | upper}` or `{price | round:2}`. The built-in
+/// filters are
+/// - `upper`, `lower`, `trim`
+/// - `round`, with the number of decimals (0 by default)
+/// - `join`, with the separator (`", "` by default)
+/// - `len`, the number of items of an array or map, or of chars of a string
+/// - `default`, replacing a missing value or empty string
+/// - `truncate`, with the max number of chars and an optional ellipsis
+///
+/// Custom filters can be registered on a [`TemplateEngine`].
+///
+/// Blocks render parts of the template conditionally or repeatedly:
+/// - `{#if path}…{#else}…{/if}` renders its first part when the value at
+///   the path is truthy (not missing, null, false, zero or empty), the
+///   optional `{#else}` part otherwise
+/// - `{#each path}…{/each}` renders its content for every item of the
+///   array or map at the path. In the loop, paths are relative to the
+///   item, `.` designates the item itself, and `@index`, `@key`,
+///   `@first` and `@last` give the position of the item
+///
+/// ```
+/// let template = iq::Template::new("test {1}");
+/// let data = ('a', 'b');
+/// assert_eq!(template.render(data), "test b");
+///
+/// let template = iq::Template::parse("{{0}} is {0}").unwrap();
+/// assert_eq!(template.render(data), "{0} is a");
+/// assert!(iq::Template::parse("{0} }").is_err());
+///
+/// let template = iq::Template::new(r#"{0}, {5 ?? "none"}, {6:-none}, {7}"#)
+///     .with_missing_marker("<missing>");
+/// assert_eq!(template.render(data), "a, none, none, <missing>");
+///
+/// let template = iq::Template::new(r#"{0 | upper}{1 | default:"z"}{7 | default:"z"}"#);
+/// assert_eq!(template.render(data), "Abz");
+///
+/// let template = iq::Template::new("{#each .}{#if @first}{.}{#else}, {.}{/if}{/each}");
+/// assert_eq!(template.render(["a", "b", "c"]), "a, b, c");
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Template {
+    tokens: Vec<Token>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    missing_marker: Option<String>,
+}
+
+impl Template {
+    /// Build a template, keeping invalid braces (eg a `{` which
+    /// isn't closed) and invalid placeholders as literal text
+    pub fn new(template: &str) -> Self {
+        let tokens = tokenize(template, false)
+            .and_then(|pieces| build_tokens(pieces, false))
+            .unwrap_or_else(|_| vec![Token::Literal(template.to_string())]); // not expected
+        Self {
+            tokens,
+            missing_marker: None,
+        }
+    }
+    /// Build a template, failing on invalid braces or placeholders
+    pub fn parse(template: &str) -> Result<Self, TemplateError> {
+        let tokens = build_tokens(tokenize(template, true)?, true)?;
+        Ok(Self {
+            tokens,
+            missing_marker: None,
+        })
+    }
+    /// Set the text rendered in place of a missing value, when
+    /// the placeholder has no default value
+    pub fn with_missing_marker<S: Into<String>>(
+        mut self,
+        marker: S,
+    ) -> Self {
+        self.missing_marker = Some(marker.into());
+        self
+    }
+    /// Render the template with the values found in data, rendering
+    /// missing values with the defaults or the missing marker
+    pub fn render<T>(
+        &self,
+        data: T,
+    ) -> String
+    where
+        T: Serialize,
+    {
+        TemplateEngine::default().render(self, data)
+    }
+    /// Render the template, failing with the list of all the placeholders
+    /// which can't be rendered and have no default value.
+    ///
+    /// The missing marker isn't used.
+    ///
+    /// ```
+    /// use iq::*;
+    /// let data = ("a", ("b", "c"));
+    /// let template = Template::new("{0}{1}{2}{2 ?? \"z\"}");
+    /// let TemplateError::Unresolved(unresolved) = template.render_strict(data).unwrap_err() else {
+    ///     panic!("should be unresolved");
+    /// };
+    /// assert_eq!(unresolved[0].path, vec!["1"]);
+    /// assert_eq!(unresolved[0].reason, UnresolvedReason::NotPrimitive);
+    /// assert_eq!(unresolved[1].path, vec!["2"]);
+    /// assert_eq!(unresolved[1].reason, UnresolvedReason::NotFound);
+    /// assert_eq!(Template::new("{0}{2 ?? \"z\"}").render_strict(data).unwrap(), "az");
+    /// ```
+    pub fn render_strict<T>(
+        &self,
+        data: T,
+    ) -> Result<String, TemplateError>
+    where
+        T: Serialize,
+    {
+        TemplateEngine::default().render_strict(self, data)
+    }
+}
+
+impl FromStr for Template {
+    type Err = TemplateError;
+    fn from_str(s: &str) -> Result<Self, TemplateError> {
+        Self::parse(s)
+    }
+}
+
+#[test]
+fn test_templates() {
+    #[derive(Serialize)]
+    struct Span<'s> {
+        text: &'s str,
+        age: u32,
+    }
+    #[derive(Serialize)]
+    struct Diagnostic {
+        disease: Option<String>,
+        diag_span: Span<'static>,
+    }
+    #[derive(Serialize)]
+    struct Data<'s> {
+        spans: Vec<Span<'s>>,
+        diag: Diagnostic,
+        stuf: (u16, u16),
+    }
+    let data = Data {
+        spans: vec![
+            Span {
+                text: "hello",
+                age: 1,
+            },
+            Span {
+                text: "world",
+                age: 2,
+            },
+        ],
+        diag: Diagnostic {
+            disease: Some("covid".to_string()),
+            diag_span: Span {
+                text: "diagnosis",
+                age: 3,
+            },
+        },
+        stuf: (4, 5),
+    };
+    let template = Template::new(
+        "spans: {spans.0.text} {spans.1.age}, diag: {diag.disease} {diag.diag_span.text}, stuf: {stuf.0} {stuf.1}",
+    );
+    assert_eq!(
+        template.render(&data),
+        "spans: hello 2, diag: covid diagnosis, stuf: 4 5",
+    );
+    let template = Template::new(
+        r#"{diag.disease ?? "unknown"}, {diag.cause ?? "unknown"}, {diag.cause:-not known}, {diag}"#,
+    );
+    assert_eq!(template.render(&data), "covid, unknown, not known, ");
+    let template = template.with_missing_marker("<missing>");
+    assert_eq!(
+        template.render(&data),
+        "covid, unknown, not known, <missing>"
+    );
+    // the marker survives a serialization round trip
+    let json = serde_json::to_string(&template).unwrap();
+    let template: Template = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        template.render(&data),
+        "covid, unknown, not known, <missing>"
+    );
+    assert_eq!(
+        Template::parse(r#"a {b ?? "c" d}"#).unwrap_err(),
+        TemplateError::InvalidPlaceholder {
+            position: 2,
+            reason: r#"unexpected "d" after a string"#.to_string(),
+        }
+    );
+    assert_eq!(
+        Template::new(r#"a {b ?? "c" d} {0}"#).render(data.stuf),
+        r#"a {b ?? "c" d} 4"#
+    );
+    // templates serialized by iq 0.4 can still be read
+    let template: Template =
+        serde_json::from_str(r#"{"tokens":[{"Literal":"stuf: "},{"IqPath":["stuf","1"]}]}"#)
+            .unwrap();
+    assert_eq!(template.render(&data), "stuf: 5");
+    // code and JSON between braces aren't placeholders
+    let template = Template::new(r#"fn f() { return {0}; } and {"a": {1}, "b": [1]} or {"c":"d"}"#);
+    assert_eq!(
+        template.render(data.stuf),
+        r#"fn f() { return 4; } and {"a": 5, "b": [1]} or {"c":"d"}"#
+    );
+    assert!(matches!(
+        Template::parse("{ return 1; }"),
+        Err(TemplateError::InvalidPlaceholder { position: 0, .. })
+    ));
+    // strict rendering
+    let template = Template::new("{diag.disease} {diag.cause} {diag} {diag.cause ?? \"-\"}");
+    let err = template.render_strict(&data).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unresolved placeholders: {diag.cause} (not found), {diag} (not a primitive)"
+    );
+    let template = Template::new("{spans.1.text} {diag.cause ?? \"-\"}");
+    assert_eq!(template.render_strict(&data).unwrap(), "world -");
+    struct Failing;
+    impl Serialize for Failing {
+        fn serialize<S: serde::Serializer>(
+            &self,
+            _serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            Err(serde::ser::Error::custom("boom"))
+        }
+    }
+    let data = ("ok", Failing);
+    assert_eq!(
+        Template::new("{0} {1} {0}")
+            .with_missing_marker("?")
+            .render(&data),
+        "ok ? ok"
+    );
+    let err = Template::new("{0} {1}").render_strict(&data).unwrap_err();
+    let TemplateError::Unresolved(unresolved) = err else {
+        panic!("unexpected error: {:?}", err);
+    };
+    assert_eq!(unresolved.len(), 1);
+    assert_eq!(unresolved[0].path, vec!["1"]);
+    assert!(matches!(
+        &unresolved[0].reason,
+        UnresolvedReason::Serialization(msg) if msg.contains("boom"),
+    ));
+}
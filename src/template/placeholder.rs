@@ -0,0 +1,170 @@
+use {
+    super::{
+        filter::FilterCall,
+        tokenizer::*,
+    },
+    crate::{
+        path::Nesting,
+        *,
+    },
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+};
+
+/// The interpreted content of a placeholder: an iq path, the filters
+/// applied to its value, and an optional default value rendered when
+/// there's no value
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "PlaceholderDef")]
+pub(crate) struct Placeholder {
+    pub path: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub filters: Vec<FilterCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+}
+
+/// What a placeholder is deserialized from: either its fields or,
+/// as serialized by iq 0.4, its path alone
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PlaceholderDef {
+    Fields {
+        path: Vec<String>,
+        #[serde(default)]
+        filters: Vec<FilterCall>,
+        #[serde(default)]
+        default: Option<String>,
+    },
+    Path(Vec<String>),
+}
+
+impl From<PlaceholderDef> for Placeholder {
+    fn from(def: PlaceholderDef) -> Self {
+        match def {
+            PlaceholderDef::Fields {
+                path,
+                filters,
+                default,
+            } => Self {
+                path,
+                filters,
+                default,
+            },
+            PlaceholderDef::Path(path) => Self {
+                path,
+                filters: Vec::new(),
+                default: None,
+            },
+        }
+    }
+}
+
+impl Placeholder {
+    /// Parse the content of a placeholder, which is
+    /// - a path, eg `driver.name`
+    /// - optionally followed by filters, eg `| upper | truncate:10`
+    /// - optionally followed by a default value, either as `?? default`
+    ///   or, when there's no filter, as `:-default`
+    ///
+    /// The default is either a double-quoted string (with `\`
+    /// escaping the next char) or some bare text, which is trimmed.
+    ///
+    /// Return the reason of the failure on error.
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let (head, mut default) = match find_unquoted(content, "??") {
+            Some(idx) => (&content[..idx], Some(parse_default(&content[idx + 2..])?)),
+            None => (content, None),
+        };
+        let mut segments = split_unquoted(head, "|").into_iter();
+        let mut path = segments.next().unwrap_or_default();
+        if default.is_none() {
+            if let Some(idx) = find_unquoted(path, ":-") {
+                default = Some(parse_default(&path[idx + 2..])?);
+                path = &path[..idx];
+            }
+        }
+        let path = path.trim();
+        if path.is_empty() {
+            return Err("no path in the placeholder".to_string());
+        }
+        let path = parse_path(path)?;
+        let filters = segments
+            .map(FilterCall::parse)
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self {
+            path,
+            filters,
+            default,
+        })
+    }
+}
+
+/// Parse the path of a placeholder or block, `.` designating the
+/// current value (the item in a loop).
+///
+/// Out of parentheses, brackets and braces, a path can't contain
+/// whitespace, `;` or `:`, so that some code or JSON between braces
+/// (eg `{ return 1; }` or `{"a":1}`) isn't taken for a placeholder.
+pub(crate) fn parse_path(path: &str) -> Result<Vec<String>, String> {
+    if path == "." {
+        return Ok(Vec::new());
+    }
+    let mut nesting = Nesting::default();
+    for c in path.chars() {
+        if nesting.is_top_level() && (c.is_whitespace() || c == ';' || c == ':') {
+            return Err(format!("invalid char {:?} in the path", c));
+        }
+        nesting.push(c);
+    }
+    Ok(path.iq_path())
+}
+
+fn parse_default(text: &str) -> Result<String, String> {
+    let text = text.trim();
+    if text.starts_with('"') {
+        unquote(text)
+    } else {
+        Ok(text.to_string())
+    }
+}
+
+#[test]
+fn test_parse_placeholder() {
+    let ok = |content| Placeholder::parse(content).unwrap();
+    let path = |p: &str| p.iq_path();
+    assert_eq!(ok(" a.b ").path, path("a.b"));
+    assert_eq!(ok("a.b").default, None);
+    let with_default = ok(r#"diag.disease ?? "unknown""#);
+    assert_eq!(with_default.path, path("diag.disease"));
+    assert_eq!(with_default.default.as_deref(), Some("unknown"));
+    assert_eq!(
+        ok("diag.disease:-unknown").default.as_deref(),
+        Some("unknown")
+    );
+    assert_eq!(ok("a ?? not known ").default.as_deref(), Some("not known"));
+    assert_eq!(ok(r#"a ?? " ?? :- ""#).default.as_deref(), Some(" ?? :- "));
+    assert_eq!(
+        ok(r#"a ?? "say \"hi\"""#).default.as_deref(),
+        Some(r#"say "hi""#)
+    );
+    assert_eq!(ok("a ??").default.as_deref(), Some(""));
+    assert_eq!(ok(r#"a."??" ?? b"#).path, path(r#"a."??""#));
+    let with_filters = ok(r#"tags | join:", " | upper ?? "none""#);
+    assert_eq!(with_filters.path, path("tags"));
+    assert_eq!(with_filters.filters.len(), 2);
+    assert_eq!(with_filters.filters[0].name, "join");
+    assert_eq!(with_filters.default.as_deref(), Some("none"));
+    assert_eq!(ok("a:-x | len").filters[0].name, "len");
+    assert_eq!(ok("a | round:-1").default, None);
+    assert!(ok(" . ").path.is_empty());
+    assert_eq!(ok(r#"grid.(3, 4)[{"x": 1}]"#).path.len(), 3);
+    assert!(Placeholder::parse("return 1;").is_err());
+    assert!(Placeholder::parse(r#""a":1"#).is_err());
+    assert!(Placeholder::parse("a | ").is_err());
+    assert!(Placeholder::parse("?? a").is_err());
+    assert!(Placeholder::parse(r#"a ?? "b" c"#).is_err());
+    assert!(Placeholder::parse(r#"a ?? "b"#).is_err());
+}
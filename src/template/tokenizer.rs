@@ -0,0 +1,240 @@
+use super::TemplateError;
+
+/// A part of a template, before the content of the placeholders
+/// is interpreted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Piece<'t> {
+    Literal(String),
+    /// The content of a placeholder, between its braces, with the
+    /// position of its opening brace
+    Placeholder {
+        content: &'t str,
+        position: usize,
+    },
+}
+
+/// Split a template into literals and placeholders.
+///
+/// The syntax is:
+/// - `{{` and `}}` are literal `{` and `}`
+/// - any other `{` opens a placeholder, closed by the next `}` which
+///   isn't in a double-quoted string
+/// - a placeholder can't contain a `{` out of its strings and can't be
+///   empty or blank
+///
+/// When `strict`, an invalid brace is an error. Otherwise it's kept
+/// as literal text.
+pub(crate) fn tokenize(
+    template: &str,
+    strict: bool,
+) -> Result<Vec<Piece<'_>>, TemplateError> {
+    let bytes = template.as_bytes();
+    let mut pieces = Vec::new();
+    let mut literal = String::new();
+    let mut i = 0;
+    while let Some(idx) = template[i..].find(['{', '}']) {
+        let pos = i + idx;
+        literal.push_str(&template[i..pos]);
+        let brace = bytes[pos];
+        if bytes.get(pos + 1) == Some(&brace) {
+            literal.push(brace as char);
+            i = pos + 2;
+            continue;
+        }
+        i = pos + 1;
+        if brace == b'}' {
+            if strict {
+                return Err(TemplateError::UnexpectedClosingBrace { position: pos });
+            }
+            literal.push('}');
+            continue;
+        }
+        let error = match placeholder_len(&template[i..]) {
+            Some(len) => {
+                let content = &template[i..i + len];
+                if content.trim().is_empty() {
+                    TemplateError::EmptyPlaceholder { position: pos }
+                } else {
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                    }
+                    pieces.push(Piece::Placeholder {
+                        content,
+                        position: pos,
+                    });
+                    i += len + 1;
+                    continue;
+                }
+            }
+            None => TemplateError::UnclosedBrace { position: pos },
+        };
+        if strict {
+            return Err(error);
+        }
+        literal.push('{');
+    }
+    literal.push_str(&template[i..]);
+    if !literal.is_empty() {
+        pieces.push(Piece::Literal(literal));
+    }
+    Ok(pieces)
+}
+
+/// Find the `}` closing a placeholder, skipping double-quoted strings.
+///
+/// Return `None` if the placeholder isn't closed or contains a `{`.
+fn placeholder_len(s: &str) -> Option<usize> {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (idx, b) in s.bytes().enumerate() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if b == b'\\' {
+                escaped = true;
+            } else if b == b'"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match b {
+            b'"' => in_string = true,
+            b'}' => return Some(idx),
+            b'{' => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parse a double-quoted string at the start of `s`, in which a
+/// backslash escapes the next char.
+///
+/// Return the unescaped string and what follows it.
+pub(crate) fn parse_quoted(s: &str) -> Option<(String, &str)> {
+    let mut chars = s.strip_prefix('"')?.char_indices();
+    let mut string = String::new();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '"' => return Some((string, &s[idx + 2..])),
+            '\\' => string.push(chars.next()?.1),
+            c => string.push(c),
+        }
+    }
+    None // not closed
+}
+
+/// Parse a text which must be exactly one double-quoted string,
+/// apart from surrounding spaces
+pub(crate) fn unquote(text: &str) -> Result<String, String> {
+    match parse_quoted(text.trim()) {
+        Some((string, rest)) if rest.trim().is_empty() => Ok(string),
+        Some((_, rest)) => Err(format!("unexpected {:?} after a string", rest.trim())),
+        None => Err("unclosed string".to_string()),
+    }
+}
+
+/// Split `s` on the occurrences of `separator` which aren't in
+/// double-quoted strings
+pub(crate) fn split_unquoted<'s>(
+    s: &'s str,
+    separator: &str,
+) -> Vec<&'s str> {
+    let mut parts = Vec::new();
+    let mut rest = s;
+    while let Some(idx) = find_unquoted(rest, separator) {
+        parts.push(&rest[..idx]);
+        rest = &rest[idx + separator.len()..];
+    }
+    parts.push(rest);
+    parts
+}
+
+/// Find the first occurrence of `pattern` which isn't in a
+/// double-quoted string
+pub(crate) fn find_unquoted(
+    s: &str,
+    pattern: &str,
+) -> Option<usize> {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (idx, c) in s.char_indices() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if s[idx..].starts_with(pattern) {
+            return Some(idx);
+        }
+    }
+    None
+}
+
+#[test]
+fn test_tokenize() {
+    fn lit(s: &str) -> Piece<'_> {
+        Piece::Literal(s.to_string())
+    }
+    fn ph(
+        content: &str,
+        position: usize,
+    ) -> Piece<'_> {
+        Piece::Placeholder { content, position }
+    }
+    let ok = |s| tokenize(s, true).unwrap();
+    assert_eq!(ok("no brace"), vec![lit("no brace")]);
+    assert_eq!(
+        ok("{a.b} and {c}"),
+        vec![ph("a.b", 0), lit(" and "), ph("c", 10)]
+    );
+    assert_eq!(ok("{{a}}"), vec![lit("{a}")]);
+    assert_eq!(ok("{{{a}}}"), vec![lit("{"), ph("a", 2), lit("}")]);
+    assert_eq!(ok("x{{}}y"), vec![lit("x{}y")]);
+    assert_eq!(ok("é{ a }"), vec![lit("é"), ph(" a ", 2)]);
+    assert_eq!(ok(""), vec![]);
+    assert_eq!(ok(r#"{a ?? "}"}"#), vec![ph(r#"a ?? "}""#, 0)]);
+    assert_eq!(ok(r#"{a ?? "\"}"}"#), vec![ph(r#"a ?? "\"}""#, 0)]);
+    let err = |s| tokenize(s, true).unwrap_err();
+    assert_eq!(
+        err("a}b"),
+        TemplateError::UnexpectedClosingBrace { position: 1 }
+    );
+    assert_eq!(err("a{b"), TemplateError::UnclosedBrace { position: 1 });
+    assert_eq!(err("{a{b}"), TemplateError::UnclosedBrace { position: 0 });
+    assert_eq!(
+        err("a { } b"),
+        TemplateError::EmptyPlaceholder { position: 2 }
+    );
+    // invalid braces are literal when not strict
+    let lenient = |s| tokenize(s, false).unwrap();
+    assert_eq!(lenient("a}b"), vec![lit("a}b")]);
+    assert_eq!(lenient("{a{b}"), vec![lit("{a"), ph("b", 2)]);
+    assert_eq!(lenient("{}"), vec![lit("{}")]);
+    assert_eq!(lenient("a{b"), vec![lit("a{b")]);
+}
+
+#[test]
+fn test_quoted() {
+    assert_eq!(
+        parse_quoted(r#""a \"b\" \\c" rest"#),
+        Some((r#"a "b" \c"#.to_string(), " rest"))
+    );
+    assert_eq!(parse_quoted(r#""é""#), Some(("é".to_string(), "")));
+    assert_eq!(parse_quoted(r#""a"#), None);
+    assert_eq!(parse_quoted("a"), None);
+    assert_eq!(find_unquoted(r#"a."??" ?? b"#, "??"), Some(7));
+    assert_eq!(find_unquoted(r#""??""#, "??"), None);
+    assert_eq!(
+        split_unquoted(r#"a | b:"|" |c"#, "|"),
+        vec!["a ", r#" b:"|" "#, "c"]
+    );
+    assert_eq!(split_unquoted("a", "|"), vec!["a"]);
+    assert_eq!(unquote(r#" "a\"" "#), Ok(r#"a""#.to_string()));
+    assert!(unquote(r#""a" b"#).is_err());
+}
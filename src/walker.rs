@@ -0,0 +1,569 @@
+use {
+    crate::{
+        errors::IqInternalError,
+        map_key::MapKey,
+        pattern::{
+            ChildKey,
+            PatternTree,
+        },
+    },
+    serde::{
+        Serialize,
+        ser,
+    },
+    std::ops::ControlFlow,
+};
+
+/// What the walker does with the values it finds
+pub(crate) trait Visit {
+    /// Called for each value matched by a terminal node of the pattern tree.
+    ///
+    /// Return `ControlFlow::Break(())` to stop the walk.
+    fn visit<T>(
+        &mut self,
+        node: usize,
+        path: &[String],
+        value: &T,
+    ) -> Result<ControlFlow<()>, IqInternalError>
+    where
+        T: ?Sized + Serialize;
+
+    /// Whether the path given to `visit` is used (when it's not, the walker
+    /// doesn't build it and gives an empty path)
+    fn needs_path(&self) -> bool {
+        true
+    }
+}
+
+/// A visitor can be lent to a walker, so that what it collected is
+/// still available when the walk fails
+impl<V: Visit> Visit for &mut V {
+    fn visit<T>(
+        &mut self,
+        node: usize,
+        path: &[String],
+        value: &T,
+    ) -> Result<ControlFlow<()>, IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        (**self).visit(node, path, value)
+    }
+    fn needs_path(&self) -> bool {
+        (**self).needs_path()
+    }
+}
+
+/// The thing which dives into a Serialize value following all the
+/// patterns of a tree at once, visiting every matching value.
+///
+/// Contrary to the `Diver`, it may go back to explore other branches,
+/// and it doesn't stop at the first match.
+pub(crate) struct Walker<'t, V> {
+    tree: &'t PatternTree,
+    visitor: V,
+    /// the pattern nodes matching the current value and its ancestors,
+    /// stacked so that entering a value doesn't allocate
+    nodes: Vec<usize>,
+    /// the index in `nodes` of the first node matching the current value
+    frame: usize,
+    /// the path to the current value
+    path: Vec<String>,
+    idx: usize,
+    /// the key of the current map entry
+    map_key: MapKey,
+    /// the index in `nodes` of the pattern nodes matching the next
+    /// map value
+    next_map_value: Option<usize>,
+}
+impl<'t, V: Visit> Walker<'t, V> {
+    pub fn new(
+        tree: &'t PatternTree,
+        visitor: V,
+    ) -> Self {
+        let mut nodes = Vec::with_capacity(32);
+        nodes.push(PatternTree::ROOT);
+        Self {
+            tree,
+            visitor,
+            nodes,
+            frame: 0,
+            path: Vec::new(),
+            idx: 0,
+            map_key: MapKey::default(),
+            next_map_value: None,
+        }
+    }
+    /// Walk the value, return the visitor
+    pub fn walk<T>(
+        mut self,
+        value: &T,
+    ) -> Result<V, IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        match self.walk_root(value) {
+            Ok(()) | Err(IqInternalError::Done) => Ok(self.visitor),
+            Err(err) => Err(err),
+        }
+    }
+    fn walk_root<T>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        if self.tree.is_terminal(PatternTree::ROOT) {
+            self.visit(PatternTree::ROOT, value)?;
+        }
+        if self.tree.has_children(PatternTree::ROOT) {
+            value.serialize(&mut *self)?;
+        }
+        Ok(())
+    }
+    fn visit<T>(
+        &mut self,
+        node: usize,
+        value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        match self.visitor.visit(node, &self.path, value)? {
+            ControlFlow::Continue(()) => Ok(()),
+            ControlFlow::Break(()) => Err(IqInternalError::Done),
+        }
+    }
+    /// Push on the stack the children of the current nodes which match
+    /// the key (or index), return the index of the first one
+    fn push_matching_children(
+        &mut self,
+        key: Key<'_>,
+    ) -> usize {
+        let start = self.nodes.len();
+        for i in self.frame..start {
+            let node = [self.nodes[i]];
+            let key = match key {
+                Key::Name(name) => ChildKey::Field(name),
+                Key::Index(idx) => ChildKey::Index(idx),
+                Key::Map => ChildKey::Map(&self.map_key),
+            };
+            self.tree
+                .push_matching_children(&node, key, &mut self.nodes);
+        }
+        start
+    }
+    /// Visit a child value (a field, an item, a map value, etc.) whose
+    /// key matches the pattern nodes stacked from `start`, then explore it
+    fn enter<T>(
+        &mut self,
+        key: Key<'_>,
+        start: usize,
+        value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        if start == self.nodes.len() {
+            return Ok(());
+        }
+        let needs_path = self.visitor.needs_path();
+        if needs_path {
+            let key = match key {
+                Key::Name(name) => name.to_string(),
+                Key::Index(idx) => idx.to_string(),
+                Key::Map => self.map_key.as_str().to_string(),
+            };
+            self.path.push(key);
+        }
+        for i in start..self.nodes.len() {
+            let child = self.nodes[i];
+            if self.tree.is_terminal(child) {
+                self.visit(child, value)?;
+            }
+        }
+        if self.nodes[start..]
+            .iter()
+            .any(|&child| self.tree.has_children(child))
+        {
+            let parent_frame = std::mem::replace(&mut self.frame, start);
+            let parent_idx = self.idx;
+            value.serialize(&mut *self)?;
+            self.frame = parent_frame;
+            self.idx = parent_idx;
+        }
+        self.nodes.truncate(start);
+        if needs_path {
+            self.path.pop();
+        }
+        Ok(())
+    }
+    fn enter_field<T>(
+        &mut self,
+        key: &str,
+        value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        let key = Key::Name(key);
+        let start = self.push_matching_children(key);
+        self.enter(key, start, value)
+    }
+    fn enter_next_item<T>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        let key = Key::Index(self.idx);
+        self.idx += 1;
+        let start = self.push_matching_children(key);
+        self.enter(key, start, value)
+    }
+    fn start_compound(&mut self) -> Result<&mut Self, IqInternalError> {
+        self.idx = 0;
+        Ok(self)
+    }
+}
+
+/// The key of a child value
+#[derive(Clone, Copy)]
+enum Key<'k> {
+    /// the name of a field
+    Name(&'k str),
+    /// the index of an item
+    Index(usize),
+    /// the current map key
+    Map,
+}
+
+impl<V: Visit> ser::Serializer for &mut Walker<'_, V> {
+    type Ok = ();
+    type Error = IqInternalError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+    fn serialize_bool(
+        self,
+        _v: bool,
+    ) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+    fn serialize_i8(
+        self,
+        _v: i8,
+    ) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+    fn serialize_i16(
+        self,
+        _v: i16,
+    ) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+    fn serialize_i32(
+        self,
+        _v: i32,
+    ) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+    fn serialize_i64(
+        self,
+        _v: i64,
+    ) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+    fn serialize_u8(
+        self,
+        _v: u8,
+    ) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+    fn serialize_u16(
+        self,
+        _v: u16,
+    ) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+    fn serialize_u32(
+        self,
+        _v: u32,
+    ) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+    fn serialize_u64(
+        self,
+        _v: u64,
+    ) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+    fn serialize_f32(
+        self,
+        _v: f32,
+    ) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+    fn serialize_f64(
+        self,
+        _v: f64,
+    ) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+    fn serialize_char(
+        self,
+        _v: char,
+    ) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+    fn serialize_str(
+        self,
+        _v: &str,
+    ) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+    fn serialize_bytes(
+        self,
+        _v: &[u8],
+    ) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+    fn serialize_none(self) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+    fn serialize_some<T>(
+        self,
+        value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+    fn serialize_unit_struct(
+        self,
+        _name: &'static str,
+    ) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+    fn serialize_newtype_struct<T>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+    fn serialize_seq(
+        self,
+        _len: Option<usize>,
+    ) -> Result<Self::SerializeSeq, IqInternalError> {
+        self.start_compound()
+    }
+    fn serialize_tuple(
+        self,
+        _len: usize,
+    ) -> Result<Self::SerializeTuple, IqInternalError> {
+        self.start_compound()
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, IqInternalError> {
+        self.start_compound()
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, IqInternalError> {
+        self.start_compound()
+    }
+    fn serialize_map(
+        self,
+        _len: Option<usize>,
+    ) -> Result<Self::SerializeMap, IqInternalError> {
+        self.start_compound()
+    }
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, IqInternalError> {
+        self.start_compound()
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, IqInternalError> {
+        self.start_compound()
+    }
+}
+impl<V: Visit> ser::SerializeSeq for &mut Walker<'_, V> {
+    type Ok = ();
+    type Error = IqInternalError;
+    fn serialize_element<T>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.enter_next_item(value)
+    }
+    fn end(self) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+}
+impl<V: Visit> ser::SerializeTuple for &mut Walker<'_, V> {
+    type Ok = ();
+    type Error = IqInternalError;
+    fn serialize_element<T>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.enter_next_item(value)
+    }
+    fn end(self) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+}
+impl<V: Visit> ser::SerializeTupleStruct for &mut Walker<'_, V> {
+    type Ok = ();
+    type Error = IqInternalError;
+    fn serialize_field<T>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.enter_next_item(value)
+    }
+    fn end(self) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+}
+impl<V: Visit> ser::SerializeTupleVariant for &mut Walker<'_, V> {
+    type Ok = ();
+    type Error = IqInternalError;
+    fn serialize_field<T>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.enter_next_item(value)
+    }
+    fn end(self) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+}
+impl<V: Visit> ser::SerializeMap for &mut Walker<'_, V> {
+    type Ok = ();
+    type Error = IqInternalError;
+    fn serialize_key<T>(
+        &mut self,
+        key: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.map_key.render(key)?;
+        let start = self.push_matching_children(Key::Map);
+        if start < self.nodes.len() {
+            self.next_map_value = Some(start);
+        }
+        Ok(())
+    }
+    fn serialize_value<T>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        let Some(start) = self.next_map_value.take() else {
+            return Ok(());
+        };
+        self.enter(Key::Map, start, value)
+    }
+    fn end(self) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+}
+impl<V: Visit> ser::SerializeStruct for &mut Walker<'_, V> {
+    type Ok = ();
+    type Error = IqInternalError;
+    fn serialize_field<T>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.enter_field(key, value)
+    }
+    fn end(self) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+}
+impl<V: Visit> ser::SerializeStructVariant for &mut Walker<'_, V> {
+    type Ok = ();
+    type Error = IqInternalError;
+    fn serialize_field<T>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), IqInternalError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.enter_field(key, value)
+    }
+    fn end(self) -> Result<(), IqInternalError> {
+        Ok(())
+    }
+}